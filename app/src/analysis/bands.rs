@@ -0,0 +1,125 @@
+use {
+	super::FFT_SIZE,
+	std::{borrow::Cow, ops::Range},
+};
+
+/// A named frequency range, in the unit of the bands it's one of.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Band {
+	pub name: Cow<'static, str>,
+	pub low: f32,
+	pub high: f32,
+}
+
+/// What band edges are measured in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+	Hertz,
+	/// Fractions of the highest frequency there is, half the sample rate, so the bands cover the
+	/// same bins whatever that is.
+	Nyquist,
+}
+
+/// The FFT size a spectrum is analysed with and the bands it is split into.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bands {
+	pub fft_size: usize,
+	pub unit: Unit,
+	pub bands: Cow<'static, [Band]>,
+}
+
+impl Bands {
+	/// What scenes get unless they ask for something else: the bins 0 to 1, 2 to 7 and 8 to 15
+	/// of a 32 point FFT the first scenes were written against.
+	pub const DEFAULT: Bands = Bands {
+		fft_size: FFT_SIZE,
+		unit: Unit::Nyquist,
+		bands: Cow::Borrowed(&[
+			Band {
+				name: Cow::Borrowed("bass"),
+				low: 0.0,
+				high: 0.125,
+			},
+			Band {
+				name: Cow::Borrowed("mid"),
+				low: 0.125,
+				high: 0.5,
+			},
+			Band {
+				name: Cow::Borrowed("treble"),
+				low: 0.5,
+				high: 1.0,
+			},
+		]),
+	};
+
+	pub fn frequency_bin_count(&self) -> usize {
+		self.fft_size / 2
+	}
+
+	/// The bins each band covers, in order, of a spectrum with `bins` bins. A band narrower
+	/// than a bin covers the bin its middle falls in.
+	pub fn ranges(&self, bins: usize, sample_rate: f32) -> Vec<Range<usize>> {
+		let width = match self.unit {
+			Unit::Hertz => sample_rate / self.fft_size as f32,
+			Unit::Nyquist => 1.0 / self.frequency_bin_count() as f32,
+		};
+		let bin = |frequency: f32| ((frequency / width).max(0.0) as usize).min(bins);
+
+		(self.bands.iter())
+			.map(|band| {
+				let (low, high) = (bin(band.low), bin(band.high));
+				if high > low {
+					low..high
+				} else {
+					let middle = bin((band.low + band.high) / 2.0);
+					middle..(middle + 1).min(bins)
+				}
+			})
+			.collect()
+	}
+
+	/// The mean of the bins each band covers, in order.
+	pub fn average(&self, spectrum: &[f32], sample_rate: f32) -> Vec<f32> {
+		(self.ranges(spectrum.len(), sample_rate).into_iter())
+			.map(|range| {
+				let bins = &spectrum[range];
+				if bins.is_empty() {
+					0.0
+				} else {
+					bins.iter().sum::<f32>() / bins.len() as f32
+				}
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn default_keeps_its_bins_at_any_sample_rate() {
+		for sample_rate in [44100.0, 48000.0] {
+			assert_eq!(
+				Bands::DEFAULT.ranges(16, sample_rate),
+				vec![0..2, 2..8, 8..16]
+			);
+		}
+	}
+
+	#[test]
+	fn hertz_follow_the_sample_rate() {
+		let bands = Bands {
+			fft_size: 32,
+			unit: Unit::Hertz,
+			bands: Cow::Borrowed(&[Band {
+				name: Cow::Borrowed("low"),
+				low: 0.0,
+				high: 5512.5,
+			}]),
+		};
+		assert_eq!(bands.ranges(16, 44100.0), vec![0..4]);
+		assert_eq!(bands.ranges(16, 22050.0), vec![0..8]);
+	}
+}
@@ -0,0 +1,81 @@
+use super::fft::fft;
+
+/// Samples chroma is worked out from. Long enough to tell semitones apart down to about 100Hz.
+pub const CHROMA_SIZE: usize = 8192;
+
+/// Pitch class names, from C up.
+pub const PITCH_CLASSES: [&str; 12] = [
+	"C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Frequencies outside this range say more about the drums than the harmony.
+const LOWEST: f32 = 55.0;
+const HIGHEST: f32 = 5000.0;
+
+/// How much of the signal's energy falls in each pitch class, from C up, scaled so the loudest is
+/// 1. All zeros for silence.
+pub fn chroma(samples: &[f32], sample_rate: f32) -> [f32; 12] {
+	let size = samples.len().next_power_of_two();
+	let mut re = vec![0.0; size];
+	for (n, (value, sample)) in re.iter_mut().zip(samples).enumerate() {
+		let phase = std::f32::consts::TAU * n as f32 / samples.len() as f32;
+		*value = sample * (0.5 - 0.5 * phase.cos());
+	}
+	let mut im = vec![0.0; size];
+	fft(&mut re, &mut im);
+
+	let mut chroma = [0.0; 12];
+	let width = sample_rate / size as f32;
+	for bin in (LOWEST / width).ceil() as usize..=(HIGHEST / width) as usize {
+		if bin >= size / 2 {
+			break;
+		}
+		let semitones = 12.0 * (bin as f32 * width / 440.0).log2();
+		// A is 9 semitones above C.
+		let class = (semitones.round() as i32 + 9).rem_euclid(12) as usize;
+		chroma[class] += re[bin] * re[bin] + im[bin] * im[bin];
+	}
+
+	let loudest = chroma.iter().copied().fold(0.0, f32::max);
+	if loudest > 0.0 {
+		chroma.iter_mut().for_each(|value| *value /= loudest);
+	}
+	chroma
+}
+
+/// The pitch class with the most energy, `None` for silence.
+pub fn dominant_pitch_class(chroma: &[f32; 12]) -> Option<usize> {
+	(0..12)
+		.filter(|&class| chroma[class] > 0.0)
+		.max_by(|&a, &b| chroma[a].total_cmp(&chroma[b]))
+}
+
+#[cfg(test)]
+mod tests {
+	use {super::*, std::f32::consts::TAU};
+
+	/// The pitch class a sine at `frequency` comes out in.
+	fn class(frequency: f32, sample_rate: f32) -> &'static str {
+		let tone = (0..CHROMA_SIZE)
+			.map(|n| (TAU * frequency * n as f32 / sample_rate).sin())
+			.collect::<Vec<_>>();
+		PITCH_CLASSES[dominant_pitch_class(&chroma(&tone, sample_rate)).unwrap()]
+	}
+
+	#[test]
+	fn tones_fall_in_their_pitch_class() {
+		for sample_rate in [44100.0, 48000.0] {
+			assert_eq!(class(440.0, sample_rate), "A");
+			// Middle C, and the G below it.
+			assert_eq!(class(261.63, sample_rate), "C");
+			assert_eq!(class(196.0, sample_rate), "G");
+		}
+	}
+
+	#[test]
+	fn silence_has_no_pitch_class() {
+		let chroma = chroma(&[0.0; CHROMA_SIZE], 44100.0);
+		assert_eq!(chroma, [0.0; 12]);
+		assert_eq!(dominant_pitch_class(&chroma), None);
+	}
+}
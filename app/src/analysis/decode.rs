@@ -0,0 +1,92 @@
+use {
+	std::{fs::File, io::ErrorKind, path::Path},
+	symphonia::core::{
+		audio::SampleBuffer, codecs::DecoderOptions, errors::Error, formats::FormatOptions,
+		io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+	},
+};
+
+/// A whole track decoded to interleaved samples.
+pub struct Decoded {
+	pub samples: Vec<f32>,
+	pub channels: u16,
+	pub sample_rate: u32,
+}
+
+impl Decoded {
+	pub fn duration(&self) -> f64 {
+		self.samples.len() as f64 / self.channels as f64 / self.sample_rate as f64
+	}
+
+	/// Averages the channels, which is how `AnalyserNode` down-mixes its input.
+	pub fn mono(&self) -> Vec<f32> {
+		(self.samples.chunks(self.channels as usize))
+			.map(|frame| frame.iter().sum::<f32>() / self.channels as f32)
+			.collect()
+	}
+
+	/// One channel's samples. Tracks with fewer channels play their last in the rest, which is
+	/// how the browser up-mixes mono to stereo.
+	pub fn channel(&self, channel: usize) -> Vec<f32> {
+		let channel = channel.min(self.channels as usize - 1);
+		(self.samples.chunks(self.channels as usize))
+			.map(|frame| frame[channel])
+			.collect()
+	}
+}
+
+pub fn decode(path: &Path) -> Result<Decoded, Error> {
+	let stream = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+	let mut hint = Hint::new();
+	if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+		hint.with_extension(extension);
+	}
+	let mut format = symphonia::default::get_probe()
+		.format(
+			&hint,
+			stream,
+			&FormatOptions::default(),
+			&MetadataOptions::default(),
+		)?
+		.format;
+	let track = format
+		.default_track()
+		.ok_or(Error::Unsupported("no audio track"))?;
+	let track_id = track.id;
+	let mut decoder =
+		symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+	let mut decoded = Decoded {
+		samples: vec![],
+		channels: track
+			.codec_params
+			.channels
+			.map_or(1, |channels| channels.count()) as u16,
+		sample_rate: track.codec_params.sample_rate.unwrap_or(44100),
+	};
+	loop {
+		let packet = match format.next_packet() {
+			Ok(packet) => packet,
+			Err(Error::IoError(error)) if error.kind() == ErrorKind::UnexpectedEof => break,
+			Err(error) => return Err(error),
+		};
+		if packet.track_id() != track_id {
+			continue;
+		}
+		match decoder.decode(&packet) {
+			Ok(buffer) => {
+				let spec = *buffer.spec();
+				decoded.channels = spec.channels.count() as u16;
+				decoded.sample_rate = spec.rate;
+				let mut samples = SampleBuffer::<f32>::new(buffer.capacity() as u64, spec);
+				samples.copy_interleaved_ref(buffer);
+				decoded.samples.extend_from_slice(samples.samples());
+			}
+			// A corrupt frame only costs its own samples.
+			Err(Error::DecodeError(_)) => continue,
+			Err(error) => return Err(error),
+		}
+	}
+
+	Ok(decoded)
+}
@@ -0,0 +1,156 @@
+use serde::Deserialize;
+
+/// How an envelope follower responds to its input. Times are in seconds, so followers behave the
+/// same whatever the frame rate.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Envelope {
+	/// Time to rise most of the way, about 63%, towards a louder input.
+	pub attack: f32,
+	/// Time to fall most of the way towards a quieter input.
+	pub release: f32,
+	/// Time a peak is held before releasing.
+	pub hold: f32,
+	/// Scales the input before it is followed.
+	pub gain: f32,
+	/// Exponent the output is raised to. Above 1 favours peaks, below 1 lifts quiet parts.
+	pub curve: f32,
+}
+
+impl Envelope {
+	pub const DEFAULT: Envelope = Envelope {
+		attack: 0.01,
+		release: 0.2,
+		hold: 0.0,
+		gain: 1.0,
+		curve: 1.0,
+	};
+}
+
+impl Default for Envelope {
+	fn default() -> Self {
+		Envelope::DEFAULT
+	}
+}
+
+/// Smooths a signal that is sampled once per frame.
+#[derive(Clone, Debug, Default)]
+pub struct Follower {
+	pub envelope: Envelope,
+	level: f32,
+	since_peak: f32,
+	rate: f32,
+}
+
+impl Follower {
+	pub fn new(envelope: Envelope) -> Self {
+		Follower {
+			envelope,
+			level: 0.0,
+			since_peak: 0.0,
+			rate: 0.0,
+		}
+	}
+
+	/// Follows `input` for the `dt` seconds since the last update and returns the new value.
+	pub fn update(&mut self, input: f32, dt: f32) -> f32 {
+		let Envelope {
+			attack,
+			release,
+			hold,
+			gain,
+			..
+		} = self.envelope;
+		let input = input * gain;
+		let last = self.value();
+
+		if input >= self.level {
+			self.level += (input - self.level) * approach(dt, attack);
+			self.since_peak = 0.0;
+		} else {
+			// The hold may run out partway through the frame.
+			let releasing = (self.since_peak + dt - hold).clamp(0.0, dt);
+			self.level += (input - self.level) * approach(releasing, release);
+			self.since_peak += dt;
+		}
+
+		if dt > 0.0 {
+			self.rate = (self.value() - last) / dt;
+		}
+		self.value()
+	}
+
+	/// The smoothed, and held, input with the curve applied.
+	pub fn value(&self) -> f32 {
+		self.level.max(0.0).powf(self.envelope.curve)
+	}
+
+	/// How fast `value` changed over the last update, per second.
+	pub fn rate(&self) -> f32 {
+		self.rate
+	}
+}
+
+/// How much of the way to its target an exponential approach with time constant `time` covers
+/// in `dt`.
+fn approach(dt: f32, time: f32) -> f32 {
+	if time <= 0.0 {
+		1.0
+	} else {
+		1.0 - (-dt / time).exp()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const ENVELOPE: Envelope = Envelope {
+		attack: 0.05,
+		release: 0.2,
+		hold: 0.1,
+		gain: 1.0,
+		curve: 1.0,
+	};
+
+	/// Where the follower should be `time` seconds into a step up to 1 for half a second and
+	/// back down to 0.
+	fn expected(time: f32) -> f32 {
+		let rise = |time: f32| 1.0 - (-time / ENVELOPE.attack).exp();
+		if time <= 0.5 {
+			rise(time)
+		} else {
+			let releasing = (time - 0.5 - ENVELOPE.hold).max(0.0);
+			rise(0.5) * (-releasing / ENVELOPE.release).exp()
+		}
+	}
+
+	#[test]
+	fn step_response_is_the_same_at_any_frame_rate() {
+		for fps in [30, 60, 144] {
+			let dt = 1.0 / fps as f32;
+			let mut follower = Follower::new(ENVELOPE);
+			for frame in 1..=fps * 3 / 2 {
+				let time = frame as f32 * dt;
+				let input = if frame <= fps / 2 { 1.0 } else { 0.0 };
+				let value = follower.update(input, dt);
+				assert!(
+					(value - expected(time)).abs() < 1e-4,
+					"{value} at {time}s and {fps}fps, expected {}",
+					expected(time)
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn gain_and_curve_shape_the_output() {
+		let mut follower = Follower::new(Envelope {
+			attack: 0.0,
+			gain: 0.5,
+			curve: 2.0,
+			..ENVELOPE
+		});
+		assert_eq!(follower.update(1.0, 1.0 / 60.0), 0.25);
+	}
+}
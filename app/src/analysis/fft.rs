@@ -0,0 +1,69 @@
+use std::f32::consts::PI;
+
+/// In-place radix-2 FFT. Both slices must have the same power of two length.
+pub fn fft(re: &mut [f32], im: &mut [f32]) {
+	let n = re.len();
+	debug_assert!(n.is_power_of_two() && im.len() == n);
+
+	// Bit reversal permutation.
+	let mut j = 0;
+	for i in 1..n {
+		let mut bit = n >> 1;
+		while j & bit != 0 {
+			j ^= bit;
+			bit >>= 1;
+		}
+		j |= bit;
+		if i < j {
+			re.swap(i, j);
+			im.swap(i, j);
+		}
+	}
+
+	// Twiddle factors for the last stage, which the earlier ones take every few of.
+	let twiddles = (0..n / 2)
+		.map(|k| (-2.0 * PI * k as f32 / n as f32).sin_cos())
+		.collect::<Vec<_>>();
+	let mut len = 2;
+	while len <= n {
+		let stride = n / len;
+		for start in (0..n).step_by(len) {
+			for k in 0..len / 2 {
+				let (sin, cos) = twiddles[k * stride];
+				let (a, b) = (start + k, start + k + len / 2);
+				let (br, bi) = (re[b] * cos - im[b] * sin, re[b] * sin + im[b] * cos);
+				re[b] = re[a] - br;
+				im[b] = im[a] - bi;
+				re[a] += br;
+				im[a] += bi;
+			}
+		}
+		len <<= 1;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn matches_the_discrete_fourier_transform() {
+		let n = 64;
+		let signal = (0..n)
+			.map(|t| (t as f32 * 0.3).sin() + 0.5 * (t as f32 * 1.7).cos() + 0.1 * t as f32)
+			.collect::<Vec<_>>();
+		let (mut re, mut im) = (signal.clone(), vec![0.0; n]);
+		fft(&mut re, &mut im);
+
+		for k in 0..n {
+			let (mut dre, mut dim) = (0.0, 0.0);
+			for (t, value) in signal.iter().enumerate() {
+				let (sin, cos) = (-2.0 * PI * (k * t) as f32 / n as f32).sin_cos();
+				dre += value * cos;
+				dim += value * sin;
+			}
+			assert!((re[k] - dre).abs() < 1e-3, "bin {k}: {} != {dre}", re[k]);
+			assert!((im[k] - dim).abs() < 1e-3, "bin {k}: {} != {dim}", im[k]);
+		}
+	}
+}
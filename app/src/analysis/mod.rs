@@ -0,0 +1,162 @@
+//! Offline analysis of decoded tracks: a stand-in for the browser's `AnalyserNode`, so spectra
+//! come out bin for bin the same as what scenes read live, and tempo estimation for working out
+//! the beat grid of new tracks.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use decode::{decode, Decoded};
+pub use {
+	bands::{Band, Bands, Unit},
+	chroma::{chroma, dominant_pitch_class, CHROMA_SIZE, PITCH_CLASSES},
+	envelope::{Envelope, Follower},
+	onsets::OnsetDetector,
+	tempo::{estimate_tempo, TempoEstimate},
+	track::{AnalysisTrack, FRAME_RATE},
+};
+
+mod bands;
+mod chroma;
+#[cfg(not(target_arch = "wasm32"))]
+mod decode;
+mod envelope;
+mod fft;
+mod onsets;
+mod tempo;
+mod track;
+
+/// Size of the FFT tracks are analysed with ahead of time, giving `FFT_SIZE / 2` frequency bins.
+/// Scenes asking for another size are analysed live.
+pub const FFT_SIZE: usize = 32;
+
+/// Emulates `AnalyserNode` with its default smoothing and decibel range: each analysed window is
+/// Blackman windowed, transformed, smoothed against the previous magnitudes and scaled into bytes
+/// between `min_decibels` and `max_decibels`.
+pub struct Analyser {
+	pub smoothing_time_constant: f32,
+	pub min_decibels: f32,
+	pub max_decibels: f32,
+
+	fft_size: usize,
+	window: Vec<f32>,
+	magnitudes: Vec<f32>,
+}
+
+impl Analyser {
+	pub fn new(fft_size: usize) -> Self {
+		assert!(fft_size.is_power_of_two() && (32..=32768).contains(&fft_size));
+		let window = (0..fft_size)
+			.map(|n| {
+				let phase = std::f32::consts::TAU * n as f32 / fft_size as f32;
+				0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos()
+			})
+			.collect();
+
+		Analyser {
+			smoothing_time_constant: 0.8,
+			min_decibels: -100.0,
+			max_decibels: -30.0,
+			fft_size,
+			window,
+			magnitudes: vec![0.0; fft_size / 2],
+		}
+	}
+
+	pub fn fft_size(&self) -> usize {
+		self.fft_size
+	}
+
+	pub fn frequency_bin_count(&self) -> usize {
+		self.fft_size / 2
+	}
+
+	/// Takes in the latest `fft_size` samples, oldest first. Shorter input is treated as silence
+	/// before it.
+	pub fn analyse(&mut self, samples: &[f32]) {
+		let samples = &samples[samples.len().saturating_sub(self.fft_size)..];
+		let padding = self.fft_size - samples.len();
+		let mut re = vec![0.0; self.fft_size];
+		let mut im = vec![0.0; self.fft_size];
+		for (n, sample) in samples.iter().enumerate() {
+			re[padding + n] = sample * self.window[padding + n];
+		}
+		fft::fft(&mut re, &mut im);
+
+		let tau = self.smoothing_time_constant;
+		for (k, magnitude) in self.magnitudes.iter_mut().enumerate() {
+			let current = (re[k] * re[k] + im[k] * im[k]).sqrt() / self.fft_size as f32;
+			*magnitude = tau * *magnitude + (1.0 - tau) * current;
+		}
+	}
+
+	/// The smoothed spectrum as `AnalyserNode::get_byte_frequency_data` would report it.
+	pub fn byte_frequency_data(&self, data: &mut [u8]) {
+		let range = self.max_decibels - self.min_decibels;
+		for (byte, magnitude) in data.iter_mut().zip(self.magnitudes.iter()) {
+			let decibels = 20.0 * magnitude.log10();
+			*byte = (255.0 / range * (decibels - self.min_decibels)).clamp(0.0, 255.0) as u8;
+		}
+	}
+}
+
+/// Byte spectra of a whole track, one per video frame.
+pub struct Spectra {
+	pub frame_rate: f64,
+	pub bins: usize,
+	data: Vec<u8>,
+}
+
+impl Spectra {
+	/// Runs `samples` through an `Analyser` polled `frame_rate` times a second, the way the app
+	/// polls it once per rendered frame. The smoothing depends on that rate, so it should match
+	/// the frame rate the result is played back at. Even no samples give a frame, of silence.
+	pub fn analyse(samples: &[f32], sample_rate: u32, fft_size: usize, frame_rate: f64) -> Self {
+		let mut analyser = Analyser::new(fft_size);
+		let bins = analyser.frequency_bin_count();
+		let frames = (samples.len() as f64 / sample_rate as f64 * frame_rate)
+			.ceil()
+			.max(1.0) as usize;
+		let mut data = vec![0; frames * bins];
+
+		for (frame, spectrum) in data.chunks_mut(bins).enumerate() {
+			let end =
+				((frame as f64 / frame_rate * sample_rate as f64) as usize).min(samples.len());
+			analyser.analyse(&samples[end.saturating_sub(fft_size)..end]);
+			analyser.byte_frequency_data(spectrum);
+		}
+
+		Spectra {
+			frame_rate,
+			bins,
+			data,
+		}
+	}
+
+	fn from_data(frame_rate: f64, bins: usize, data: Vec<u8>) -> Self {
+		Spectra {
+			frame_rate,
+			bins,
+			data,
+		}
+	}
+
+	fn data(&self) -> &[u8] {
+		&self.data
+	}
+
+	pub fn len(&self) -> usize {
+		self.data.len() / self.bins
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.data.is_empty()
+	}
+
+	pub fn frame(&self, index: usize) -> &[u8] {
+		&self.data[index * self.bins..(index + 1) * self.bins]
+	}
+
+	/// The spectrum at `time` seconds into the track, holding the last one past the end.
+	pub fn at(&self, time: f64) -> &[u8] {
+		let index = (time.max(0.0) * self.frame_rate) as usize;
+		self.frame(index.min(self.len().saturating_sub(1)))
+	}
+}
@@ -0,0 +1,116 @@
+/// Picks onsets out of a band as its spectrum comes in, frame by frame: an onset is the band's
+/// spectral flux jumping well above what it has recently been.
+#[derive(Clone, Debug)]
+pub struct OnsetDetector {
+	/// Standard deviations above its recent mean the flux has to jump.
+	pub sensitivity: f32,
+	/// Flux that never counts, so noise in quiet parts doesn't trigger.
+	pub floor: f32,
+	/// Seconds the mean and deviation are taken over.
+	pub window: f32,
+	/// Seconds after an onset before the next one can trigger.
+	pub refractory: f32,
+
+	last: Vec<f32>,
+	mean: f32,
+	variance: f32,
+	since_onset: f32,
+}
+
+impl OnsetDetector {
+	pub fn new() -> Self {
+		OnsetDetector {
+			sensitivity: 2.0,
+			floor: 0.02,
+			window: 0.5,
+			refractory: 0.1,
+			last: vec![],
+			mean: 0.0,
+			variance: 0.0,
+			since_onset: f32::INFINITY,
+		}
+	}
+
+	/// Takes this frame's bins of the band, `dt` seconds after the last, and returns the
+	/// strength of the onset they start, from 0 to 1, if they start one.
+	pub fn update(&mut self, bins: &[f32], dt: f32) -> Option<f32> {
+		let flux = if self.last.len() == bins.len() && !bins.is_empty() {
+			(bins.iter().zip(&self.last))
+				.map(|(value, last)| (value - last).max(0.0))
+				.sum::<f32>()
+				/ bins.len() as f32
+		} else {
+			0.0
+		};
+		self.last.clear();
+		self.last.extend_from_slice(bins);
+		self.since_onset += dt;
+
+		let threshold = self.mean + self.sensitivity * self.variance.sqrt() + self.floor;
+		let onset = (flux > threshold && self.since_onset >= self.refractory).then(|| {
+			self.since_onset = 0.0;
+			1.0 - threshold / flux
+		});
+
+		// Exponentially weighted, so the window is the same length whatever the frame rate.
+		let weight = 1.0 - (-dt / self.window).exp();
+		let deviation = flux - self.mean;
+		self.mean += weight * deviation;
+		self.variance = (1.0 - weight) * (self.variance + weight * deviation * deviation);
+
+		onset
+	}
+}
+
+impl Default for OnsetDetector {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Frame rates a screen might refresh at.
+	const FRAME_RATES: [f32; 3] = [30.0, 60.0, 144.0];
+
+	/// Onsets a new detector finds over `seconds` of frames at `fps`, with `bins` giving the band
+	/// at each time.
+	fn onsets(fps: f32, seconds: f32, bins: impl Fn(f32) -> Vec<f32>) -> Vec<(f32, f32)> {
+		let mut detector = OnsetDetector::new();
+		(0..(seconds * fps).round() as usize)
+			.filter_map(|frame| {
+				let time = frame as f32 / fps;
+				(detector.update(&bins(time), 1.0 / fps)).map(|strength| (time, strength))
+			})
+			.collect()
+	}
+
+	#[test]
+	fn a_burst_after_silence_is_one_onset() {
+		for fps in FRAME_RATES {
+			// A second of silence, a fifth of a second of sound, and silence again.
+			let found = onsets(fps, 2.0, |time| {
+				vec![if (1.0..1.2).contains(&time) { 0.8 } else { 0.0 }; 8]
+			});
+			assert_eq!(found.len(), 1, "{fps} fps: {found:?}");
+			let (time, strength) = found[0];
+			assert!((time - 1.0).abs() < 1.5 / fps, "{fps} fps: at {time}");
+			assert!(0.0 < strength && strength <= 1.0, "{fps} fps: {strength}");
+		}
+	}
+
+	#[test]
+	fn a_steady_tone_has_no_onsets() {
+		for fps in FRAME_RATES {
+			// A little flicker from bin to bin and frame to frame, as analysis has.
+			let found = onsets(fps, 3.0, |time| {
+				(0..8)
+					.map(|bin| 0.5 + 0.005 * (40.0 * time + bin as f32).sin())
+					.collect()
+			});
+			assert_eq!(found, [], "{fps} fps");
+		}
+	}
+}
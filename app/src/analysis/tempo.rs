@@ -0,0 +1,261 @@
+use {super::fft, crate::tracks::BeatGrid, std::f32::consts::TAU};
+
+// Onset detection frames: about 6ms apart at 44.1kHz, each looking at about 23ms of audio.
+const FRAME_SIZE: usize = 1024;
+const HOP: usize = 256;
+
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 180.0;
+/// Octave errors are the usual failure, so tempos are weighted towards this one.
+const PREFERRED_BPM: f64 = 120.0;
+
+/// Frequencies below this make up the accents downbeats are told apart by.
+const LOW_BAND_HZ: f32 = 200.0;
+
+/// A suggested beat grid for a track, and how many whole bars it holds.
+#[derive(Clone, Copy, Debug)]
+pub struct TempoEstimate {
+	pub bpm: f64,
+	/// Seconds to the first downbeat.
+	pub offset: f64,
+	pub beats_per_bar: usize,
+	/// Bars from the first downbeat to the end of the track, to the nearest whole one.
+	pub measures: usize,
+}
+
+impl TempoEstimate {
+	pub fn grid(&self) -> BeatGrid {
+		BeatGrid {
+			bpm: self.bpm,
+			offset: self.offset,
+			beats_per_bar: self.beats_per_bar,
+			tempo_changes: &[],
+		}
+	}
+
+	/// Every beat in a track `duration` seconds long, including any before the first downbeat.
+	pub fn beats(&self, duration: f64) -> Vec<f64> {
+		let period = 60.0 / self.bpm;
+		let first = self.offset - (self.offset / period).floor() * period;
+		(0..)
+			.map(|beat| first + beat as f64 * period)
+			.take_while(|&time| time < duration)
+			.collect()
+	}
+}
+
+/// Estimates a steady tempo and the first downbeat of `mono`, assuming `beats_per_bar` beats to
+/// a bar. The tempo is the period the onsets repeat at most strongly, the beats are placed where
+/// that period lines up with the most onsets, and the downbeat is the beat of the bar with the
+/// strongest low end.
+pub fn estimate_tempo(mono: &[f32], sample_rate: u32, beats_per_bar: usize) -> TempoEstimate {
+	let (onsets, low) = onset_envelopes(mono, sample_rate);
+	let frame_rate = sample_rate as f64 / HOP as f64;
+	let duration = mono.len() as f64 / sample_rate as f64;
+
+	// Coarse search over the whole range, then a finer one around the best candidate. Onsets
+	// half a beat apart count against a tempo, since they mean it is too slow.
+	let score = |bpm: f64, multiples: usize| {
+		let period = 60.0 / bpm * frame_rate;
+		let repeats = (1..=multiples)
+			.map(|multiple| autocorrelation(&onsets, multiple as f64 * period) / multiple as f64)
+			.sum::<f64>();
+		(repeats - autocorrelation(&onsets, period / 2.0) / 4.0)
+			* (-0.5 * (bpm / PREFERRED_BPM).log2().powi(2)).exp()
+	};
+	let best = |candidates: &mut dyn Iterator<Item = f64>, multiples| {
+		candidates
+			.map(|bpm| (bpm, score(bpm, multiples)))
+			.max_by(|a, b| a.1.total_cmp(&b.1))
+			.map_or(PREFERRED_BPM, |(bpm, _)| bpm)
+	};
+	let coarse = best(
+		&mut (0..=((MAX_BPM - MIN_BPM) * 10.0) as usize).map(|step| MIN_BPM + step as f64 / 10.0),
+		2,
+	);
+	let bpm = best(
+		&mut (0..=20).map(|step| coarse - 0.1 + step as f64 / 100.0),
+		8,
+	);
+
+	let period = 60.0 / bpm * frame_rate;
+	let phase = (0..(period * 4.0) as usize)
+		.map(|step| step as f64 / 4.0)
+		.max_by(|&a, &b| comb(&onsets, a, period).total_cmp(&comb(&onsets, b, period)))
+		.unwrap_or(0.0);
+	let bar = period * beats_per_bar as f64;
+	let downbeat = (0..beats_per_bar)
+		.map(|beat| phase + beat as f64 * period)
+		.max_by(|&a, &b| comb(&low, a, bar).total_cmp(&comb(&low, b, bar)))
+		.unwrap_or(phase);
+
+	// Frames start a whole frame early so that onsets right at the start are seen rising, and
+	// a frame's flux peaks about a quarter of a frame before the onset it picked up.
+	let offset = (downbeat * HOP as f64 - 0.75 * FRAME_SIZE as f64) / sample_rate as f64;
+	let offset = offset.max(0.0);
+	let bar_duration = 60.0 / bpm * beats_per_bar as f64;
+	TempoEstimate {
+		bpm,
+		offset,
+		beats_per_bar,
+		measures: ((duration - offset) / bar_duration).round().max(1.0) as usize,
+	}
+}
+
+/// Log-magnitude spectral flux over the whole spectrum and over the low band, one value per hop,
+/// with the first frame ending where the audio starts.
+fn onset_envelopes(mono: &[f32], sample_rate: u32) -> (Vec<f32>, Vec<f32>) {
+	let window = (0..FRAME_SIZE)
+		.map(|n| 0.5 - 0.5 * (TAU * n as f32 / FRAME_SIZE as f32).cos())
+		.collect::<Vec<_>>();
+	let low_bins = (LOW_BAND_HZ * FRAME_SIZE as f32 / sample_rate as f32).ceil() as usize;
+
+	let frames = (mono.len() + FRAME_SIZE) / HOP;
+	let (mut onsets, mut low) = (vec![0.0; frames], vec![0.0; frames]);
+	let mut previous = vec![0.0; FRAME_SIZE / 2];
+	for frame in 0..frames {
+		let start = (frame * HOP) as isize - FRAME_SIZE as isize;
+		let mut re = vec![0.0; FRAME_SIZE];
+		let mut im = vec![0.0; FRAME_SIZE];
+		for (n, value) in re.iter_mut().enumerate() {
+			if let Some(sample) = mono.get((start + n as isize) as usize) {
+				*value = sample * window[n];
+			}
+		}
+		fft::fft(&mut re, &mut im);
+
+		for (k, previous) in previous.iter_mut().enumerate() {
+			let magnitude = (1.0 + 100.0 * (re[k] * re[k] + im[k] * im[k]).sqrt()).ln();
+			let rise = (magnitude - *previous).max(0.0);
+			*previous = magnitude;
+			onsets[frame] += rise;
+			if (1..=low_bins).contains(&k) {
+				low[frame] += rise;
+			}
+		}
+	}
+
+	(onsets, low)
+}
+
+/// Linearly interpolated value of `envelope` at a fractional frame.
+fn sample(envelope: &[f32], frame: f64) -> f32 {
+	let index = frame as usize;
+	let fraction = (frame - index as f64) as f32;
+	let next = envelope.get(index + 1).copied().unwrap_or(0.0);
+	envelope
+		.get(index)
+		.map_or(0.0, |&value| value * (1.0 - fraction) + next * fraction)
+}
+
+/// How much `envelope` resembles itself `lag` frames later, around its mean.
+fn autocorrelation(envelope: &[f32], lag: f64) -> f64 {
+	let mean = envelope.iter().sum::<f32>() / envelope.len().max(1) as f32;
+	let count = envelope.len().saturating_sub(lag.ceil() as usize + 1);
+	let sum = (0..count)
+		.map(|frame| {
+			((envelope[frame] - mean) * (sample(envelope, frame as f64 + lag) - mean)) as f64
+		})
+		.sum::<f64>();
+	sum / count.max(1) as f64
+}
+
+/// Average of `envelope` every `period` frames starting from `phase`.
+fn comb(envelope: &[f32], phase: f64, period: f64) -> f64 {
+	let teeth = ((envelope.len() as f64 - phase) / period).ceil().max(1.0) as usize;
+	let sum = (0..teeth)
+		.map(|tooth| sample(envelope, phase + tooth as f64 * period) as f64)
+		.sum::<f64>();
+	sum / teeth as f64
+}
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{analysis::decode, assets::STATIC_DIR, tracks},
+		std::path::Path,
+	};
+
+	/// A click on every beat of a steady grid, with the downbeats lower and louder, to check
+	/// `estimate_tempo` against a known answer. Beats before `offset` are clicked too.
+	fn click_track(
+		bpm: f64,
+		offset: f64,
+		beats_per_bar: usize,
+		duration: f64,
+		sample_rate: u32,
+	) -> Vec<f32> {
+		let mut samples = vec![0.0; (duration * sample_rate as f64) as usize];
+		let period = 60.0 / bpm;
+		let click_length = sample_rate as usize / 20;
+
+		let mut beat = -(offset / period).floor() as i64;
+		loop {
+			let start = ((offset + beat as f64 * period) * sample_rate as f64) as usize;
+			if start >= samples.len() {
+				break;
+			}
+			let (frequency, gain) = if beat.rem_euclid(beats_per_bar as i64) == 0 {
+				(100.0, 1.0)
+			} else {
+				(1000.0, 0.5)
+			};
+			for (n, sample) in samples[start..].iter_mut().take(click_length).enumerate() {
+				let time = n as f32 / sample_rate as f32;
+				*sample += gain * (TAU * frequency * time).sin() * (-60.0 * time).exp();
+			}
+			beat += 1;
+		}
+
+		samples
+	}
+
+	fn assert_recovers(bpm: f64, offset: f64, beats_per_bar: usize) {
+		let sample_rate = 44100;
+		let samples = click_track(bpm, offset, beats_per_bar, 30.0, sample_rate);
+		let estimate = estimate_tempo(&samples, sample_rate, beats_per_bar);
+		assert!(
+			(estimate.bpm - bpm).abs() < 0.1,
+			"{} BPM, expected {bpm}",
+			estimate.bpm
+		);
+		// A few hops either way, about a frame at 60fps.
+		assert!(
+			(estimate.offset - offset).abs() < 0.02,
+			"offset {}, expected {offset}",
+			estimate.offset
+		);
+	}
+
+	#[test]
+	fn recovers_110_bpm_in_six_four() {
+		assert_recovers(110.0, 0.8, 6);
+	}
+
+	#[test]
+	fn recovers_62_5_bpm_in_four_four() {
+		assert_recovers(62.5, 1.5, 4);
+	}
+
+	#[test]
+	fn agrees_with_the_grids_of_the_hosted_tracks() {
+		for path in ["/bu/2.mp3", "/fa/2.mp3"] {
+			let grid = tracks::grid(path).unwrap();
+			let decoded = decode(Path::new(&format!("{STATIC_DIR}{path}"))).unwrap();
+			let estimate = estimate_tempo(&decoded.mono(), decoded.sample_rate, grid.beats_per_bar);
+			assert!(
+				(estimate.bpm - grid.bpm).abs() < 0.1,
+				"{path}: {} BPM, expected {}",
+				estimate.bpm,
+				grid.bpm
+			);
+			assert!(
+				(estimate.offset - grid.offset).abs() < 0.02,
+				"{path}: offset {}, expected {}",
+				estimate.offset,
+				grid.offset
+			);
+		}
+	}
+}
@@ -0,0 +1,185 @@
+use {
+	super::{estimate_tempo, Spectra, FFT_SIZE},
+	crate::reader::Reader,
+};
+
+/// How often analysis tracks sample the audio, matching the frame rate scenes render at.
+pub const FRAME_RATE: f64 = 60.0;
+
+const MAGIC: &[u8; 4] = b"GTA1";
+
+/// Everything scenes read from the audio, precomputed per frame so that every playthrough looks
+/// the same. Stored next to each track with the `.analysis` extension.
+pub struct AnalysisTrack {
+	/// Byte spectra, bin-compatible with the live analyser.
+	pub spectra: Spectra,
+	/// Spectral flux per frame, from 0 to 1.
+	pub onsets: Vec<f32>,
+	/// Times of the estimated beats, in seconds.
+	pub beats: Vec<f64>,
+}
+
+impl AnalysisTrack {
+	/// Where the analysis of the track served at `path` is served.
+	pub fn path_for(path: &str) -> String {
+		let stem = path.rsplit_once('.').map_or(path, |(stem, _)| stem);
+		format!("{stem}.analysis")
+	}
+
+	pub fn analyse(mono: &[f32], sample_rate: u32) -> Self {
+		let spectra = Spectra::analyse(mono, sample_rate, FFT_SIZE, FRAME_RATE);
+		let onsets = onset_envelope(&spectra);
+		let duration = mono.len() as f64 / sample_rate as f64;
+		let beats = estimate_tempo(mono, sample_rate, 4).beats(duration);
+
+		AnalysisTrack {
+			spectra,
+			onsets,
+			beats,
+		}
+	}
+
+	/// The onset strength at `time` seconds into the track.
+	pub fn onset_at(&self, time: f64) -> f32 {
+		let index = (time.max(0.0) * self.spectra.frame_rate) as usize;
+		self.onsets.get(index).copied().unwrap_or(0.0)
+	}
+
+	/// Little endian: magic, frame rate as `f32`, bin count as `u16`, frame and beat counts as
+	/// `u32`, then the spectra, one onset byte per frame and the beats as `f32` seconds.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let spectra = &self.spectra;
+		let mut bytes = MAGIC.to_vec();
+		bytes.extend((spectra.frame_rate as f32).to_le_bytes());
+		bytes.extend((spectra.bins as u16).to_le_bytes());
+		bytes.extend((spectra.len() as u32).to_le_bytes());
+		bytes.extend((self.beats.len() as u32).to_le_bytes());
+		bytes.extend(spectra.data());
+		bytes.extend(self.onsets.iter().map(|&onset| (onset * 255.0) as u8));
+		bytes.extend(
+			self.beats
+				.iter()
+				.flat_map(|&beat| (beat as f32).to_le_bytes()),
+		);
+		bytes
+	}
+
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+		let mut reader = Reader(bytes);
+		if reader.take(4)? != MAGIC {
+			return Err("not an analysis track");
+		}
+		let frame_rate = f32::from_le_bytes(reader.array()?) as f64;
+		let bins = u16::from_le_bytes(reader.array()?) as usize;
+		let frames = u32::from_le_bytes(reader.array()?) as usize;
+		let beats = u32::from_le_bytes(reader.array()?) as usize;
+		if !(frame_rate > 0.0 && frame_rate.is_finite()) {
+			return Err("bad frame rate");
+		}
+		if bins == 0 {
+			return Err("no frequency bins");
+		}
+		if frames == 0 {
+			return Err("no frames");
+		}
+
+		let length = frames.checked_mul(bins).ok_or("too many frames")?;
+		let spectra = Spectra::from_data(frame_rate, bins, reader.take(length)?.to_vec());
+		let onsets = (reader.take(frames)?.iter())
+			.map(|&onset| onset as f32 / 255.0)
+			.collect();
+		let beats = (0..beats)
+			.map(|_| Ok(f32::from_le_bytes(reader.array()?) as f64))
+			.collect::<Result<_, _>>()?;
+		if !reader.0.is_empty() {
+			return Err("trailing bytes");
+		}
+
+		Ok(AnalysisTrack {
+			spectra,
+			onsets,
+			beats,
+		})
+	}
+}
+
+/// Positive spectral flux between consecutive frames, normalised to the loudest one.
+fn onset_envelope(spectra: &Spectra) -> Vec<f32> {
+	let mut flux = (1..spectra.len())
+		.map(|frame| {
+			(spectra.frame(frame).iter())
+				.zip(spectra.frame(frame - 1))
+				.map(|(&current, &previous)| current.saturating_sub(previous) as f32)
+				.sum()
+		})
+		.collect::<Vec<f32>>();
+	flux.insert(0, 0.0);
+	let max = flux.iter().copied().fold(0.0, f32::max);
+	if max > 0.0 {
+		flux.iter_mut().for_each(|value| *value /= max);
+	}
+	flux
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn track() -> AnalysisTrack {
+		let data = (0..4 * 16).map(|n| (n * 7 % 256) as u8).collect();
+		AnalysisTrack {
+			spectra: Spectra::from_data(FRAME_RATE, 16, data),
+			onsets: vec![0.0, 0.5, 1.0, 0.25],
+			beats: vec![0.5, 1.0, 1.5],
+		}
+	}
+
+	#[test]
+	fn round_trip() {
+		let track = track();
+		let read = AnalysisTrack::from_bytes(&track.to_bytes()).unwrap();
+		assert_eq!(read.spectra.frame_rate, FRAME_RATE);
+		assert_eq!(read.spectra.bins, 16);
+		assert_eq!(read.spectra.data(), track.spectra.data());
+		for (read, onset) in read.onsets.iter().zip(&track.onsets) {
+			assert!((read - onset).abs() <= 1.0 / 255.0);
+		}
+		assert_eq!(read.beats, track.beats);
+	}
+
+	#[test]
+	fn silence_round_trips() {
+		let track = AnalysisTrack::analyse(&[], 44100);
+		let read = AnalysisTrack::from_bytes(&track.to_bytes()).unwrap();
+		assert_eq!(read.spectra.at(1.0), &[0; FFT_SIZE / 2]);
+	}
+
+	#[test]
+	fn rejects_truncated_and_trailing_bytes() {
+		let bytes = track().to_bytes();
+		for length in 0..bytes.len() {
+			assert!(AnalysisTrack::from_bytes(&bytes[..length]).is_err());
+		}
+		let trailing = [&bytes[..], &[0]].concat();
+		assert_eq!(
+			AnalysisTrack::from_bytes(&trailing).err(),
+			Some("trailing bytes")
+		);
+	}
+
+	#[test]
+	fn rejects_corrupt_headers() {
+		let bytes = track().to_bytes();
+		let corrupt = |offset: usize, field: &[u8]| {
+			let mut bytes = bytes.clone();
+			bytes[offset..offset + field.len()].copy_from_slice(field);
+			AnalysisTrack::from_bytes(&bytes).err()
+		};
+		assert_eq!(corrupt(0, b"GTA0"), Some("not an analysis track"));
+		assert_eq!(corrupt(4, &f32::NAN.to_le_bytes()), Some("bad frame rate"));
+		assert_eq!(corrupt(4, &0f32.to_le_bytes()), Some("bad frame rate"));
+		assert_eq!(corrupt(8, &0u16.to_le_bytes()), Some("no frequency bins"));
+		assert_eq!(corrupt(10, &0u32.to_le_bytes()), Some("no frames"));
+		assert_eq!(corrupt(10, &u32::MAX.to_le_bytes()), Some("truncated"));
+	}
+}
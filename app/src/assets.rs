@@ -0,0 +1,126 @@
+//! Files the server hosts under `static/`. The browser fetches them, the native build reads them
+//! straight from the directory.
+
+use std::{
+	cell::{Ref, RefCell},
+	fmt::Display,
+	rc::Rc,
+};
+#[cfg(target_arch = "wasm32")]
+use {
+	wasm_bindgen::{JsCast, JsValue},
+	wasm_bindgen_futures::JsFuture,
+	web_sys::{Request, RequestInit, RequestMode, Response},
+};
+
+/// Where the server hosts from, relative to this crate.
+#[cfg(not(target_arch = "wasm32"))]
+pub const STATIC_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../static");
+
+/// A file that is loading, parsed, or failed to load or parse.
+pub struct Asset<T>(Rc<RefCell<Option<Result<T, String>>>>);
+
+impl<T: 'static> Asset<T> {
+	/// Starts loading `path` as the server hosts it and parses it once it arrives.
+	pub fn load<E: Display>(
+		path: &str,
+		parse: impl FnOnce(&[u8]) -> Result<T, E> + 'static,
+	) -> Self {
+		Self::start(path, move |bytes| {
+			parse(bytes.ok_or("not found")?).map_err(|error| error.to_string())
+		})
+	}
+
+	/// Starts loading `path` as `load` does, for files only some tracks have. It's `None` if the
+	/// server doesn't host it, rather than an error.
+	pub fn load_optional<E: Display>(
+		path: &str,
+		parse: impl FnOnce(&[u8]) -> Result<T, E> + 'static,
+	) -> Asset<Option<T>> {
+		Asset::start(path, move |bytes| {
+			(bytes.map(parse).transpose()).map_err(|error| error.to_string())
+		})
+	}
+
+	/// Starts loading `path`, and parses it, or that it isn't hosted, once that's known.
+	fn start(path: &str, parse: impl FnOnce(Option<&[u8]>) -> Result<T, String> + 'static) -> Self {
+		let asset = Asset(Rc::new(RefCell::new(None)));
+		let state = asset.0.clone();
+		let location = path.to_owned();
+		let finish = move |bytes: Result<Option<Vec<u8>>, String>| {
+			let parsed = bytes.and_then(|bytes| parse(bytes.as_deref()));
+			state.replace(Some(parsed.map_err(|error| format!("{location}: {error}"))));
+		};
+
+		#[cfg(not(target_arch = "wasm32"))]
+		finish(match std::fs::read(format!("{STATIC_DIR}{path}")) {
+			Ok(bytes) => Ok(Some(bytes)),
+			Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+			Err(error) => Err(error.to_string()),
+		});
+		#[cfg(target_arch = "wasm32")]
+		{
+			let path = path.to_owned();
+			wasm_bindgen_futures::spawn_local(async move {
+				let bytes = fetch(&path).await;
+				finish(
+					bytes
+						.map(|buffer| {
+							buffer.map(|buffer| js_sys::Uint8Array::new(&buffer).to_vec())
+						})
+						.map_err(|error| format!("{error:?}")),
+				);
+			});
+		}
+
+		asset
+	}
+
+	pub fn get(&self) -> Option<Ref<'_, T>> {
+		Ref::filter_map(self.0.borrow(), |state| state.as_ref()?.as_ref().ok()).ok()
+	}
+
+	pub fn error(&self) -> Option<String> {
+		self.0.borrow().as_ref()?.as_ref().err().cloned()
+	}
+}
+
+/// Fetches `path` from the server as it is, without parsing it. It's `None` if the server doesn't
+/// host it.
+#[cfg(target_arch = "wasm32")]
+pub async fn fetch(path: &str) -> Result<Option<js_sys::ArrayBuffer>, JsValue> {
+	let mut init = RequestInit::new();
+	init.method("GET").mode(RequestMode::SameOrigin);
+	let request = Request::new_with_str_and_init(path, &init)?;
+	let response: Response =
+		JsFuture::from(web_sys::window().unwrap().fetch_with_request(&request))
+			.await?
+			.dyn_into()?;
+	if response.status() == 404 {
+		return Ok(None);
+	}
+	if !response.ok() {
+		return Err(JsValue::from_str(&format!("HTTP {}", response.status())));
+	}
+	Ok(Some(
+		JsFuture::from(response.array_buffer()?).await?.dyn_into()?,
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn optional_files_can_be_missing() {
+		let parse = |_: &[u8]| Ok::<_, &str>(());
+		let optional = Asset::load_optional("/fa/1.mid", parse);
+		assert!(optional.get().unwrap().is_none());
+		assert_eq!(optional.error(), None);
+
+		let required = Asset::load("/fa/1.mid", parse);
+		assert_eq!(required.error().unwrap(), "/fa/1.mid: not found");
+		let hosted = Asset::load_optional("/fa/1.analysis", parse);
+		assert!(hosted.get().unwrap().is_some());
+	}
+}
@@ -0,0 +1,222 @@
+use {
+	super::{AudioSource, Fade, Playhead, TrackId},
+	std::{cell::RefCell, rc::Rc},
+};
+
+/// Everything the model asked of a `MockAudio`, in order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call {
+	Load(String),
+	Resume,
+	/// A track, whether it loops, and when on the clock it starts.
+	Play(TrackId, bool, f64),
+	Pause(TrackId, f64),
+	Fade(TrackId, Fade),
+	SetFftSize(usize),
+	Seek(TrackId, f64),
+}
+
+struct Track {
+	path: String,
+	duration: f64,
+	ready: bool,
+	error: Option<String>,
+	playhead: Playhead,
+	fade: Fade,
+}
+
+#[derive(Default)]
+struct Script {
+	clock: f64,
+	durations: Vec<(String, f64)>,
+	tracks: Vec<Track>,
+	spectrum: Vec<u8>,
+	/// Left and right, when they have been set apart from `spectrum`.
+	channel_spectra: Option<[Vec<u8>; 2]>,
+	waveform: Vec<f32>,
+	calls: Vec<Call>,
+}
+
+/// Scripted backend. Time only passes on `advance` and the spectrum is whatever was last set.
+/// Clones share their state, so a test can keep one to script and inspect while the model owns
+/// another.
+#[derive(Clone, Default)]
+pub struct MockAudio(Rc<RefCell<Script>>);
+
+impl MockAudio {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Gives tracks loaded from `path` a length; tracks without one are 0 seconds long.
+	pub fn with_track(self, path: &str, duration: f64) -> Self {
+		self.0
+			.borrow_mut()
+			.durations
+			.push((path.to_owned(), duration));
+		self
+	}
+
+	/// Tracks start out ready, this holds one back as if it were still loading.
+	pub fn set_ready(&self, track: TrackId, ready: bool) {
+		self.0.borrow_mut().tracks[track].ready = ready;
+	}
+
+	/// Fails `track` as if it couldn't be fetched or decoded.
+	pub fn fail(&self, track: TrackId, error: &str) {
+		let mut script = self.0.borrow_mut();
+		script.tracks[track].ready = false;
+		script.tracks[track].error = Some(error.to_owned());
+	}
+
+	pub fn set_spectrum(&self, spectrum: &[u8]) {
+		self.0.borrow_mut().spectrum = spectrum.to_vec();
+	}
+
+	/// Splits the spectrum into channels; until this is called both are `spectrum`.
+	pub fn set_channel_spectra(&self, left: &[u8], right: &[u8]) {
+		self.0.borrow_mut().channel_spectra = Some([left.to_vec(), right.to_vec()]);
+	}
+
+	/// The samples `time_domain_data` ends with, latest last.
+	pub fn set_waveform(&self, waveform: &[f32]) {
+		self.0.borrow_mut().waveform = waveform.to_vec();
+	}
+
+	/// Moves the clock, and with it every playing track, `seconds` ahead.
+	pub fn advance(&self, seconds: f64) {
+		self.0.borrow_mut().clock += seconds;
+	}
+
+	/// Where `track` was loaded from.
+	pub fn path(&self, track: TrackId) -> String {
+		self.0.borrow().tracks[track].path.clone()
+	}
+
+	/// Gain of `track` at the current clock.
+	pub fn gain(&self, track: TrackId) -> f32 {
+		let script = self.0.borrow();
+		script.tracks[track].fade.gain(script.clock)
+	}
+
+	pub fn calls(&self) -> Vec<Call> {
+		self.0.borrow().calls.clone()
+	}
+}
+
+impl AudioSource for MockAudio {
+	fn load(&mut self, path: &str) -> TrackId {
+		let mut script = self.0.borrow_mut();
+		script.calls.push(Call::Load(path.to_owned()));
+		let duration = (script.durations.iter())
+			.find(|(known, _)| known == path)
+			.map_or(0.0, |&(_, duration)| duration);
+		script.tracks.push(Track {
+			path: path.to_owned(),
+			duration,
+			ready: true,
+			error: None,
+			playhead: Playhead::default(),
+			fade: Fade::FULL,
+		});
+		script.tracks.len() - 1
+	}
+
+	fn resume(&mut self) {
+		self.0.borrow_mut().calls.push(Call::Resume);
+	}
+
+	fn ready(&self, track: TrackId) -> bool {
+		self.0.borrow().tracks[track].ready
+	}
+
+	fn error(&self, track: TrackId) -> Option<String> {
+		self.0.borrow().tracks[track].error.clone()
+	}
+
+	fn paused(&self, track: TrackId) -> bool {
+		let script = self.0.borrow();
+		let track = &script.tracks[track];
+		track.playhead.paused(script.clock, track.duration)
+	}
+
+	fn play_at(&mut self, track: TrackId, loop_: bool, when: f64) {
+		let mut script = self.0.borrow_mut();
+		script.calls.push(Call::Play(track, loop_, when));
+		let clock = script.clock;
+		let track = &mut script.tracks[track];
+		track.playhead.play_at(clock, when, track.duration, loop_);
+	}
+
+	fn pause_at(&mut self, track: TrackId, when: f64) {
+		let mut script = self.0.borrow_mut();
+		script.calls.push(Call::Pause(track, when));
+		let clock = script.clock;
+		let track = &mut script.tracks[track];
+		track.playhead.pause_at(clock, when, track.duration);
+	}
+
+	fn fade(&mut self, track: TrackId, fade: Fade) {
+		let mut script = self.0.borrow_mut();
+		script.calls.push(Call::Fade(track, fade));
+		script.tracks[track].fade = fade;
+	}
+
+	fn seek(&mut self, track: TrackId, time: f64) {
+		let mut script = self.0.borrow_mut();
+		script.calls.push(Call::Seek(track, time));
+		let clock = script.clock;
+		let track = &mut script.tracks[track];
+		track.playhead.seek(clock, time, track.duration);
+	}
+
+	fn clock(&self) -> f64 {
+		self.0.borrow().clock
+	}
+
+	fn current_time(&self, track: TrackId) -> f64 {
+		let script = self.0.borrow();
+		let track = &script.tracks[track];
+		track.playhead.time(script.clock, track.duration)
+	}
+
+	fn duration(&self, track: TrackId) -> f64 {
+		self.0.borrow().tracks[track].duration
+	}
+
+	fn set_fft_size(&mut self, fft_size: usize) {
+		self.0.borrow_mut().calls.push(Call::SetFftSize(fft_size));
+	}
+
+	/// The spectrum is made up, so it may as well be CD audio's.
+	fn sample_rate(&self) -> f32 {
+		44100.0
+	}
+
+	fn frequency_data(&self, data: &mut [u8]) {
+		let script = self.0.borrow();
+		data.fill(0);
+		for (byte, &value) in data.iter_mut().zip(script.spectrum.iter()) {
+			*byte = value;
+		}
+	}
+
+	fn channel_frequency_data(&self, channel: usize, data: &mut [u8]) {
+		let script = self.0.borrow();
+		let spectrum =
+			(script.channel_spectra.as_ref()).map_or(&script.spectrum, |spectra| &spectra[channel]);
+		data.fill(0);
+		for (byte, &value) in data.iter_mut().zip(spectrum.iter()) {
+			*byte = value;
+		}
+	}
+
+	/// The end of the waveform that was last set, lined up with the end of `data`.
+	fn time_domain_data(&self, data: &mut [f32]) {
+		let script = self.0.borrow();
+		data.fill(0.0);
+		for (sample, &value) in (data.iter_mut().rev()).zip(script.waveform.iter().rev()) {
+			*sample = value;
+		}
+	}
+}
@@ -0,0 +1,123 @@
+//! Scene audio behind the `AudioSource` trait. The browser build plays through Web Audio, the
+//! native build through `rodio`, and in tests `MockAudio` follows a script so the model can be
+//! driven without either.
+
+#[cfg(test)]
+pub use mock::{Call, MockAudio};
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::NativeAudio;
+use playhead::Playhead;
+#[cfg(target_arch = "wasm32")]
+pub use web::WebAudio;
+
+#[cfg(test)]
+mod mock;
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+mod playhead;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+/// Handle for a track an `AudioSource` has loaded.
+pub type TrackId = usize;
+
+/// A track's gain going from `from` to `to` over the `length` seconds after `start` on the audio
+/// clock. It follows an equal-power curve, so a track fading out and another fading in over the
+/// same time keep the overall loudness.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fade {
+	pub start: f64,
+	pub length: f64,
+	pub from: f32,
+	pub to: f32,
+}
+
+impl Fade {
+	/// Stays at full gain.
+	pub const FULL: Fade = Fade {
+		start: 0.0,
+		length: 0.0,
+		from: 1.0,
+		to: 1.0,
+	};
+
+	pub fn gain(&self, clock: f64) -> f32 {
+		let progress = if self.length > 0.0 {
+			((clock - self.start) / self.length).clamp(0.0, 1.0) as f32
+		} else if clock < self.start {
+			0.0
+		} else {
+			1.0
+		};
+		let angle = progress * std::f32::consts::FRAC_PI_2;
+		self.from * angle.cos() + self.to * angle.sin()
+	}
+}
+
+impl Default for Fade {
+	fn default() -> Self {
+		Fade::FULL
+	}
+}
+
+pub trait AudioSource {
+	/// Starts loading the track served at `path`.
+	fn load(&mut self, path: &str) -> TrackId;
+
+	/// Lets playback start; browsers keep audio suspended until the first user gesture.
+	fn resume(&mut self);
+
+	fn ready(&self, track: TrackId) -> bool;
+	/// Why `track` failed to load, if it did. A track that failed is never ready.
+	fn error(&self, track: TrackId) -> Option<String>;
+	fn paused(&self, track: TrackId) -> bool;
+
+	/// Starts `track` at `when` on the audio clock, or right away if that has passed. Looping
+	/// tracks start over with no gap.
+	fn play_at(&mut self, track: TrackId, loop_: bool, when: f64);
+	/// Pauses `track` at `when` on the audio clock, or right away if that has passed.
+	fn pause_at(&mut self, track: TrackId, when: f64);
+	/// Replaces whatever fade `track` had.
+	fn fade(&mut self, track: TrackId, fade: Fade);
+
+	fn play(&mut self, track: TrackId, loop_: bool) {
+		self.play_at(track, loop_, self.clock());
+	}
+
+	fn pause(&mut self, track: TrackId) {
+		self.pause_at(track, self.clock());
+	}
+
+	fn seek(&mut self, track: TrackId, time: f64);
+
+	/// Seconds on the clock audio is played by, which keeps running while tracks are paused.
+	fn clock(&self) -> f64;
+
+	/// Playback position in seconds.
+	fn current_time(&self, track: TrackId) -> f64;
+	/// Track length in seconds.
+	fn duration(&self, track: TrackId) -> f64;
+
+	/// Sets the size of the FFT `frequency_data` comes from, a power of two from 32 to 32768.
+	fn set_fft_size(&mut self, fft_size: usize);
+	/// Sample rate of what `frequency_data` analyses, which its bins are spread over half of.
+	fn sample_rate(&self) -> f32;
+
+	/// Fills `data` with the spectrum of everything playing, one byte per bin, the way
+	/// `AnalyserNode::get_byte_frequency_data` does.
+	fn frequency_data(&self, data: &mut [u8]);
+	/// Like `frequency_data`, of just the left (0) or right (1) channel. Mono tracks play in both.
+	fn channel_frequency_data(&self, channel: usize, data: &mut [u8]);
+	/// Fills `data` with the latest samples of everything playing, mixed down, oldest first and
+	/// from -1 to 1, the way `AnalyserNode::get_float_time_domain_data` does.
+	fn time_domain_data(&self, data: &mut [f32]);
+}
+
+/// The backend for the platform being built for.
+pub fn platform() -> Box<dyn AudioSource> {
+	#[cfg(not(target_arch = "wasm32"))]
+	let audio = NativeAudio::new();
+	#[cfg(target_arch = "wasm32")]
+	let audio = WebAudio::new();
+	Box::new(audio)
+}
@@ -0,0 +1,370 @@
+use {
+	super::{AudioSource, Fade, Playhead, TrackId},
+	crate::{
+		analysis::{self, Analyser, Decoded, FFT_SIZE},
+		assets::STATIC_DIR,
+	},
+	rodio::{OutputStream, OutputStreamHandle, Sink, Source},
+	std::{
+		cell::RefCell,
+		iter,
+		path::Path,
+		sync::{Arc, Mutex},
+		time::{Duration, Instant},
+	},
+};
+
+struct Element {
+	/// Interleaved, shared with whatever the sink is playing instead of copied for every start.
+	samples: Arc<[f32]>,
+	channels: u16,
+	sample_rate: u32,
+	duration: f64,
+	mono: Vec<f32>,
+	stereo: [Vec<f32>; 2],
+
+	sink: Option<Sink>,
+	playhead: Playhead,
+	/// Shared with whatever the sink is playing, so fades can change while it plays.
+	fade: Arc<Mutex<Fade>>,
+	/// Audio clock time the sink stops at, shared the same way so a stop can be moved without
+	/// starting over.
+	stop: Arc<Mutex<Option<f64>>>,
+	/// Why the track didn't decode, if it didn't. It is silent and empty then.
+	error: Option<String>,
+}
+
+impl Element {
+	fn load(path: &str) -> Self {
+		let (decoded, error) = match analysis::decode(Path::new(&format!("{STATIC_DIR}{path}"))) {
+			Ok(decoded) => (decoded, None),
+			Err(error) => {
+				let silence = Decoded {
+					samples: vec![],
+					channels: 1,
+					sample_rate: 44100,
+				};
+				(silence, Some(format!("{path}: {error}")))
+			}
+		};
+		let (mono, stereo) = (decoded.mono(), [decoded.channel(0), decoded.channel(1)]);
+		let duration = decoded.duration();
+		let Decoded {
+			samples,
+			channels,
+			sample_rate,
+		} = decoded;
+
+		Element {
+			samples: samples.into(),
+			channels,
+			sample_rate,
+			duration,
+			mono,
+			stereo,
+			sink: None,
+			playhead: Playhead::default(),
+			fade: Arc::new(Mutex::new(Fade::FULL)),
+			stop: Arc::new(Mutex::new(None)),
+			error,
+		}
+	}
+
+	fn duration(&self) -> f64 {
+		self.duration
+	}
+
+	/// Replaces the sink with one that plays what the playhead has scheduled from `clock` on.
+	/// `rodio` can't move a start once it's queued, so any other change means starting over.
+	fn restart(&mut self, handle: &OutputStreamHandle, clock: f64) {
+		self.sink = None;
+		let Some(start_at) = self.playhead.start_at() else {
+			return;
+		};
+		let begin = start_at.max(clock);
+		let (channels, sample_rate) = (self.channels, self.sample_rate);
+		let time = self.playhead.time(begin, self.duration());
+		let start = (time * sample_rate as f64) as usize * channels as usize;
+
+		let samples = Samples {
+			samples: self.samples.clone(),
+			position: start.min(self.samples.len()),
+			loop_: self.playhead.loop_,
+			channels,
+			sample_rate,
+		};
+		*self.stop.lock().unwrap() = self.playhead.stop_at();
+		let source = Faded {
+			source: samples.delay(Duration::from_secs_f64(begin - clock)),
+			fade: self.fade.clone(),
+			stop: self.stop.clone(),
+			clock,
+			step: 1.0 / (sample_rate as f64 * channels as f64),
+		};
+
+		let sink = Sink::try_new(handle).unwrap();
+		sink.append(source);
+		self.sink = Some(sink);
+	}
+}
+
+/// A track's samples from `position` on, over and over if it loops.
+struct Samples {
+	samples: Arc<[f32]>,
+	/// Index of the next sample.
+	position: usize,
+	loop_: bool,
+	channels: u16,
+	sample_rate: u32,
+}
+
+impl Iterator for Samples {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<f32> {
+		if self.position >= self.samples.len() {
+			if !self.loop_ || self.samples.is_empty() {
+				return None;
+			}
+			self.position = 0;
+		}
+		self.position += 1;
+		Some(self.samples[self.position - 1])
+	}
+}
+
+impl Source for Samples {
+	fn current_frame_len(&self) -> Option<usize> {
+		None
+	}
+
+	fn channels(&self) -> u16 {
+		self.channels
+	}
+
+	fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		None
+	}
+}
+
+/// Applies a track's fade to its samples and ends them at its stop, keeping time by counting
+/// them.
+struct Faded<S> {
+	source: S,
+	fade: Arc<Mutex<Fade>>,
+	stop: Arc<Mutex<Option<f64>>>,
+	/// Audio clock time of the next sample.
+	clock: f64,
+	step: f64,
+}
+
+impl<S: Source<Item = f32>> Iterator for Faded<S> {
+	type Item = f32;
+
+	fn next(&mut self) -> Option<f32> {
+		if (*self.stop.lock().unwrap()).is_some_and(|stop| self.clock >= stop) {
+			return None;
+		}
+		let sample = self.source.next()?;
+		let gain = self.fade.lock().unwrap().gain(self.clock);
+		self.clock += self.step;
+		Some(sample * gain)
+	}
+}
+
+impl<S: Source<Item = f32>> Source for Faded<S> {
+	fn current_frame_len(&self) -> Option<usize> {
+		self.source.current_frame_len()
+	}
+
+	fn channels(&self) -> u16 {
+		self.source.channels()
+	}
+
+	fn sample_rate(&self) -> u32 {
+		self.source.sample_rate()
+	}
+
+	fn total_duration(&self) -> Option<Duration> {
+		self.source.total_duration()
+	}
+}
+
+pub struct NativeAudio {
+	// Playback stops when the stream is dropped.
+	_stream: OutputStream,
+	handle: OutputStreamHandle,
+	created: Instant,
+	elements: Vec<Element>,
+	analyser: RefCell<(Vec<usize>, Analyser)>,
+	/// Left and right, sized along with `analyser`.
+	channel_analysers: [RefCell<(Vec<usize>, Analyser)>; 2],
+}
+
+impl NativeAudio {
+	pub fn new() -> Self {
+		let (stream, handle) = OutputStream::try_default().unwrap();
+
+		NativeAudio {
+			_stream: stream,
+			handle,
+			created: Instant::now(),
+			elements: vec![],
+			analyser: RefCell::new((vec![], Analyser::new(FFT_SIZE))),
+			channel_analysers: [(); 2].map(|_| RefCell::new((vec![], Analyser::new(FFT_SIZE)))),
+		}
+	}
+
+	/// Mixes down the `length` samples just before the playback position of every playing track,
+	/// at the gain each is faded to, of one channel or, without one, of them all averaged. Also
+	/// returns where each track's samples ended, which only changes when there is something new
+	/// to mix.
+	fn mix(&self, length: usize, channel: Option<usize>) -> (Vec<f32>, Vec<usize>) {
+		let clock = self.clock();
+		let mut window = vec![0.0; length];
+		let mut ends = vec![];
+		for element in &self.elements {
+			let started = (element.playhead.start_at()).is_some_and(|start_at| start_at <= clock);
+			if !started || element.playhead.paused(clock, element.duration()) {
+				continue;
+			}
+			let time = element.playhead.time(clock, element.duration());
+			let end = (time * element.sample_rate as f64) as usize;
+			let samples = channel.map_or(&element.mono, |channel| &element.stereo[channel]);
+			let end = end.clamp(length, samples.len().max(length));
+			ends.push(end);
+
+			let samples = samples.get(end - length..end).unwrap_or(&[]);
+			let gain = element.fade.lock().unwrap().gain(clock);
+			for (mixed, sample) in window.iter_mut().zip(samples) {
+				*mixed += sample * gain;
+			}
+		}
+		(window, ends)
+	}
+
+	/// Runs a mixdown through the same `Analyser` offline analysis uses.
+	fn analyse(
+		&self,
+		analyser: &RefCell<(Vec<usize>, Analyser)>,
+		channel: Option<usize>,
+		data: &mut [u8],
+	) {
+		let mut analyser = analyser.borrow_mut();
+		let (last_ends, analyser) = &mut *analyser;
+		let (window, ends) = self.mix(analyser.fft_size(), channel);
+
+		// Like the browser, only smooth again once there are new samples to look at.
+		if *last_ends != ends {
+			*last_ends = ends;
+			analyser.analyse(&window);
+		}
+
+		analyser.byte_frequency_data(data);
+	}
+}
+
+impl Default for NativeAudio {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl AudioSource for NativeAudio {
+	fn load(&mut self, path: &str) -> TrackId {
+		self.elements.push(Element::load(path));
+		self.elements.len() - 1
+	}
+
+	fn resume(&mut self) {}
+
+	/// Tracks are decoded on load, so they are ready as soon as they exist, unless they failed.
+	fn ready(&self, track: TrackId) -> bool {
+		self.elements[track].error.is_none()
+	}
+
+	fn error(&self, track: TrackId) -> Option<String> {
+		self.elements[track].error.clone()
+	}
+
+	fn paused(&self, track: TrackId) -> bool {
+		let element = &self.elements[track];
+		element.playhead.paused(self.clock(), element.duration())
+	}
+
+	fn play_at(&mut self, track: TrackId, loop_: bool, when: f64) {
+		let clock = self.clock();
+		let element = &mut self.elements[track];
+		let duration = element.duration();
+		element.playhead.play_at(clock, when, duration, loop_);
+		element.restart(&self.handle, clock);
+	}
+
+	fn pause_at(&mut self, track: TrackId, when: f64) {
+		let clock = self.clock();
+		let element = &mut self.elements[track];
+		let duration = element.duration();
+		element.playhead.pause_at(clock, when, duration);
+		// Restarting a sink that is playing clicks, so a later stop just moves where it ends.
+		if when > clock && element.sink.is_some() {
+			*element.stop.lock().unwrap() = element.playhead.stop_at();
+		} else {
+			element.restart(&self.handle, clock);
+		}
+	}
+
+	fn fade(&mut self, track: TrackId, fade: Fade) {
+		*self.elements[track].fade.lock().unwrap() = fade;
+	}
+
+	fn seek(&mut self, track: TrackId, time: f64) {
+		let clock = self.clock();
+		let element = &mut self.elements[track];
+		let duration = element.duration();
+		element.playhead.seek(clock, time, duration);
+		element.restart(&self.handle, clock);
+	}
+
+	fn clock(&self) -> f64 {
+		self.created.elapsed().as_secs_f64()
+	}
+
+	fn current_time(&self, track: TrackId) -> f64 {
+		let element = &self.elements[track];
+		element.playhead.time(self.clock(), element.duration())
+	}
+
+	fn duration(&self, track: TrackId) -> f64 {
+		self.elements[track].duration()
+	}
+
+	fn set_fft_size(&mut self, fft_size: usize) {
+		for analyser in iter::once(&self.analyser).chain(&self.channel_analysers) {
+			let mut analyser = analyser.borrow_mut();
+			if analyser.1.fft_size() != fft_size {
+				*analyser = (vec![], Analyser::new(fft_size));
+			}
+		}
+	}
+
+	/// Tracks are analysed at their own rates, the first one's stands in for all of them.
+	fn sample_rate(&self) -> f32 {
+		(self.elements.first()).map_or(44100.0, |element| element.sample_rate as f32)
+	}
+
+	fn frequency_data(&self, data: &mut [u8]) {
+		self.analyse(&self.analyser, None, data);
+	}
+
+	fn channel_frequency_data(&self, channel: usize, data: &mut [u8]) {
+		self.analyse(&self.channel_analysers[channel], Some(channel), data);
+	}
+
+	fn time_domain_data(&self, data: &mut [f32]) {
+		data.copy_from_slice(&self.mix(data.len(), None).0);
+	}
+}
@@ -0,0 +1,79 @@
+/// Where a track is, worked out from the audio clock rather than asked of whatever plays it, so
+/// that starts and stops can be scheduled ahead of time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Playhead {
+	/// Clock time playback starts or started at, `None` while paused.
+	start_at: Option<f64>,
+	/// Position in the track at `start_at`, or while paused.
+	offset: f64,
+	/// Clock time a scheduled pause takes effect at.
+	stop_at: Option<f64>,
+	pub loop_: bool,
+}
+
+impl Playhead {
+	/// Position at `clock`, in seconds.
+	pub fn time(&self, clock: f64, duration: f64) -> f64 {
+		let Some(start_at) = self.start_at else {
+			return self.offset;
+		};
+		let clock = self.stop_at.map_or(clock, |stop_at| clock.min(stop_at));
+		let time = self.offset + (clock - start_at).max(0.0);
+		if !duration.is_finite() || duration <= 0.0 {
+			time
+		} else if self.loop_ {
+			time % duration
+		} else {
+			time.min(duration)
+		}
+	}
+
+	/// Whether nothing is playing or about to, counting a track that ran out as paused.
+	pub fn paused(&self, clock: f64, duration: f64) -> bool {
+		self.start_at.is_none()
+			|| self.stop_at.is_some_and(|stop_at| stop_at <= clock)
+			|| (!self.loop_ && self.time(clock, duration) >= duration)
+	}
+
+	/// Clock time playback starts or started at, `None` while paused.
+	pub fn start_at(&self) -> Option<f64> {
+		self.start_at
+	}
+
+	/// Clock time a pause is scheduled for.
+	pub fn stop_at(&self) -> Option<f64> {
+		self.stop_at
+	}
+
+	/// Plays from the current position from `when` on, or right away if `when` has passed. A
+	/// track that ran out starts over.
+	pub fn play_at(&mut self, clock: f64, when: f64, duration: f64, loop_: bool) {
+		self.offset = self.time(clock, duration);
+		if !loop_ && self.offset >= duration {
+			self.offset = 0.0;
+		}
+		self.start_at = Some(when.max(clock));
+		self.stop_at = None;
+		self.loop_ = loop_;
+	}
+
+	/// Pauses at `when`, or right away if `when` has passed.
+	pub fn pause_at(&mut self, clock: f64, when: f64, duration: f64) {
+		if when <= clock {
+			self.offset = self.time(clock, duration);
+			self.start_at = None;
+			self.stop_at = None;
+		} else if self.start_at.is_some() {
+			self.stop_at = Some(when);
+		}
+	}
+
+	/// Jumps to `time`, carrying on playing if it was.
+	pub fn seek(&mut self, clock: f64, time: f64, duration: f64) {
+		if self.paused(clock, duration) {
+			self.pause_at(clock, clock, duration);
+		}
+		self.offset = time.clamp(0.0, duration.max(0.0));
+		self.start_at = self.start_at.map(|start_at| start_at.max(clock));
+	}
+}
@@ -0,0 +1,264 @@
+use {
+	super::{AudioSource, Fade, Playhead, TrackId},
+	crate::{analysis::FFT_SIZE, assets},
+	std::{cell::RefCell, rc::Rc},
+	wasm_bindgen::{JsCast, JsValue},
+	wasm_bindgen_futures::JsFuture,
+	web_sys::{
+		AnalyserNode, AudioBuffer, AudioBufferSourceNode, AudioContext, ChannelCountMode,
+		ChannelInterpretation, GainNode,
+	},
+};
+
+/// Points fades are sampled at to hand them to the browser.
+const FADE_POINTS: usize = 64;
+
+/// A track decoded up front, so it can start and loop on an exact time on the context's clock.
+/// Source nodes only play once, so every start gets a new one.
+struct Track {
+	buffer: Rc<RefCell<Option<AudioBuffer>>>,
+	/// Why the track failed to fetch or decode, if it did.
+	error: Rc<RefCell<Option<String>>>,
+	source: Option<AudioBufferSourceNode>,
+	/// Stays connected to the analysers while sources come and go.
+	gain: GainNode,
+	playhead: Playhead,
+}
+
+impl Track {
+	fn duration(&self) -> f64 {
+		self.buffer
+			.borrow()
+			.as_ref()
+			.map_or(f64::NAN, |buffer| buffer.duration())
+	}
+}
+
+pub struct WebAudio {
+	analyser: AnalyserNode,
+	/// Sized to the waveform asked for, apart from `analyser`, whose size sets the spectrum's.
+	waveform: AnalyserNode,
+	/// Mixes tracks into stereo and splits that into left and right for `channels`. Splitters
+	/// take their input channel by channel, so a mono track would only reach the left; mixing
+	/// it up to two speaker channels first copies it to both, and it sits in the middle.
+	stereo: GainNode,
+	/// Left and right, sized along with `analyser`.
+	channels: [AnalyserNode; 2],
+	context: AudioContext,
+	tracks: Vec<Track>,
+}
+
+impl WebAudio {
+	pub fn new() -> Self {
+		let context = AudioContext::new().unwrap();
+		let analyser = AnalyserNode::new(&context).unwrap();
+		analyser.set_fft_size(FFT_SIZE as u32);
+		let _ = analyser.connect_with_audio_node(&context.destination());
+		let waveform = AnalyserNode::new(&context).unwrap();
+		let stereo = GainNode::new(&context).unwrap();
+		stereo.set_channel_count(2);
+		stereo.set_channel_count_mode(ChannelCountMode::Explicit);
+		stereo.set_channel_interpretation(ChannelInterpretation::Speakers);
+		let splitter = context
+			.create_channel_splitter_with_number_of_outputs(2)
+			.unwrap();
+		let _ = stereo.connect_with_audio_node(&splitter);
+		let channels = [0, 1].map(|output| {
+			let channel = AnalyserNode::new(&context).unwrap();
+			channel.set_fft_size(FFT_SIZE as u32);
+			let _ = splitter.connect_with_audio_node_and_output(&channel, output);
+			channel
+		});
+
+		WebAudio {
+			analyser,
+			waveform,
+			stereo,
+			channels,
+			context,
+			tracks: vec![],
+		}
+	}
+
+	/// Replaces the track's source node with one that plays what its playhead has scheduled.
+	fn restart(&mut self, track: TrackId) {
+		let clock = self.clock();
+		let track = &mut self.tracks[track];
+		if let Some(source) = track.source.take() {
+			let _ = source.stop();
+		}
+		let buffer = track.buffer.borrow();
+		let (Some(start_at), Some(buffer)) = (track.playhead.start_at(), buffer.as_ref()) else {
+			return;
+		};
+
+		let source = self.context.create_buffer_source().unwrap();
+		source.set_buffer(Some(buffer));
+		source.set_loop(track.playhead.loop_);
+		let _ = source.connect_with_audio_node(&track.gain);
+		let begin = start_at.max(clock);
+		let offset = track.playhead.time(begin, buffer.duration());
+		let _ = source.start_with_when_and_grain_offset(begin, offset);
+		if let Some(stop_at) = track.playhead.stop_at() {
+			let _ = source.stop_with_when(stop_at);
+		}
+		track.source = Some(source);
+	}
+}
+
+impl Default for WebAudio {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl AudioSource for WebAudio {
+	fn load(&mut self, path: &str) -> TrackId {
+		let (buffer, error) = (Rc::new(RefCell::new(None)), Rc::new(RefCell::new(None)));
+		let (context, decoded, failed) = (self.context.clone(), buffer.clone(), error.clone());
+		let path = path.to_owned();
+		wasm_bindgen_futures::spawn_local(async move {
+			match decode(&context, &path).await {
+				Ok(buffer) => {
+					decoded.replace(Some(buffer));
+				}
+				Err(error) => {
+					web_sys::console::error_2(&JsValue::from(path.as_str()), &error);
+					failed.replace(Some(format!("{path}: {error:?}")));
+				}
+			}
+		});
+
+		let gain = GainNode::new(&self.context).unwrap();
+		let _ = gain.connect_with_audio_node(&self.analyser);
+		let _ = gain.connect_with_audio_node(&self.waveform);
+		let _ = gain.connect_with_audio_node(&self.stereo);
+
+		self.tracks.push(Track {
+			buffer,
+			error,
+			source: None,
+			gain,
+			playhead: Playhead::default(),
+		});
+		self.tracks.len() - 1
+	}
+
+	fn resume(&mut self) {
+		let _ = self.context.resume();
+	}
+
+	fn ready(&self, track: TrackId) -> bool {
+		self.tracks[track].buffer.borrow().is_some()
+	}
+
+	fn error(&self, track: TrackId) -> Option<String> {
+		self.tracks[track].error.borrow().clone()
+	}
+
+	fn paused(&self, track: TrackId) -> bool {
+		let track = &self.tracks[track];
+		track.playhead.paused(self.clock(), track.duration())
+	}
+
+	fn play_at(&mut self, track: TrackId, loop_: bool, when: f64) {
+		if !self.ready(track) {
+			return;
+		}
+		let clock = self.clock();
+		let playing = &mut self.tracks[track];
+		let duration = playing.duration();
+		playing.playhead.play_at(clock, when, duration, loop_);
+		self.restart(track);
+	}
+
+	fn pause_at(&mut self, track: TrackId, when: f64) {
+		let clock = self.clock();
+		let playing = &mut self.tracks[track];
+		let duration = playing.duration();
+		playing.playhead.pause_at(clock, when, duration);
+		// Stopping later doesn't need a new source, and replacing one that is playing clicks.
+		match playing.source.as_ref().filter(|_| when > clock) {
+			Some(source) => {
+				let _ = source.stop_with_when(when);
+			}
+			None => self.restart(track),
+		}
+	}
+
+	fn fade(&mut self, track: TrackId, fade: Fade) {
+		let gain = self.tracks[track].gain.gain();
+		let _ = gain.cancel_scheduled_values(0.0);
+		let _ = gain.set_value_at_time(fade.from, fade.start);
+		if fade.length > 0.0 {
+			let mut curve = (0..=FADE_POINTS)
+				.map(|point| {
+					fade.gain(fade.start + fade.length * point as f64 / FADE_POINTS as f64)
+				})
+				.collect::<Vec<_>>();
+			let _ = gain.set_value_curve_at_time(&mut curve, fade.start, fade.length);
+		} else {
+			let _ = gain.set_value_at_time(fade.to, fade.start);
+		}
+	}
+
+	fn seek(&mut self, track: TrackId, time: f64) {
+		let clock = self.clock();
+		let playing = &mut self.tracks[track];
+		let duration = playing.duration();
+		playing.playhead.seek(clock, time, duration);
+		self.restart(track);
+	}
+
+	fn clock(&self) -> f64 {
+		self.context.current_time()
+	}
+
+	fn current_time(&self, track: TrackId) -> f64 {
+		let track = &self.tracks[track];
+		track.playhead.time(self.clock(), track.duration())
+	}
+
+	fn duration(&self, track: TrackId) -> f64 {
+		self.tracks[track].duration()
+	}
+
+	fn set_fft_size(&mut self, fft_size: usize) {
+		for analyser in std::iter::once(&self.analyser).chain(&self.channels) {
+			analyser.set_fft_size(fft_size as u32);
+		}
+	}
+
+	fn sample_rate(&self) -> f32 {
+		self.context.sample_rate()
+	}
+
+	fn frequency_data(&self, data: &mut [u8]) {
+		self.analyser.get_byte_frequency_data(data);
+	}
+
+	fn channel_frequency_data(&self, channel: usize, data: &mut [u8]) {
+		self.channels[channel].get_byte_frequency_data(data);
+	}
+
+	fn time_domain_data(&self, data: &mut [f32]) {
+		let size = data.len().next_power_of_two().clamp(32, 32768);
+		if self.waveform.fft_size() as usize != size {
+			self.waveform.set_fft_size(size as u32);
+		}
+		let mut samples = vec![0.0; size];
+		self.waveform.get_float_time_domain_data(&mut samples);
+		// Line the ends up, whichever is longer.
+		data.fill(0.0);
+		for (sample, &value) in (data.iter_mut().rev()).zip(samples.iter().rev()) {
+			*sample = value;
+		}
+	}
+}
+
+async fn decode(context: &AudioContext, path: &str) -> Result<AudioBuffer, JsValue> {
+	let bytes = assets::fetch(path).await?.ok_or("not found")?;
+	JsFuture::from(context.decode_audio_data(&bytes)?)
+		.await?
+		.dyn_into()
+}
@@ -0,0 +1,76 @@
+//! Precomputes the analysis the app plays scenes from, writing it next to each track:
+//!
+//! ```sh
+//! cargo run --release --bin analyse -- ../static/fa/1.mp3 ../static/fa/2.mp3
+//! ```
+//!
+//! writes `../static/fa/1.analysis` and `../static/fa/2.analysis`, which the server then hosts
+//! alongside the mp3s.
+//!
+//! For a new track, `tempo` estimates its beat grid and prints it ready to paste into
+//! `tracks.rs`, along with the `measures` for its scenes:
+//!
+//! ```sh
+//! cargo run --release --bin analyse -- tempo --beats-per-bar 4 ../static/tf/1.mp3
+//! ```
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+	use {
+		gender::analysis::{self, AnalysisTrack},
+		std::{env, fs, path::Path},
+	};
+
+	let mut args = env::args().skip(1).peekable();
+	let tempo = args.next_if_eq("tempo").is_some();
+	let beats_per_bar = match args.next_if_eq("--beats-per-bar") {
+		Some(_) => args
+			.next()
+			.and_then(|count| count.parse().ok())
+			.expect("--beats-per-bar takes a number"),
+		None => 4,
+	};
+
+	for path in args {
+		let path = Path::new(&path);
+		let decoded = analysis::decode(path)
+			.unwrap_or_else(|error| panic!("couldn't decode {}: {error}", path.display()));
+
+		if tempo {
+			let estimate =
+				analysis::estimate_tempo(&decoded.mono(), decoded.sample_rate, beats_per_bar);
+			// Tracks are known by the path the server hosts them at.
+			let served = path.to_string_lossy();
+			let served = served
+				.split_once("static/")
+				.map_or(&*served, |(_, served)| served);
+			println!(
+				"{}: {:.2} BPM, first downbeat at {:.3}s, {} measures",
+				path.display(),
+				estimate.bpm,
+				estimate.offset,
+				estimate.measures
+			);
+			println!(
+				"\tTrack {{\n\t\tpath: \"/{served}\",\n\t\tgrid: BeatGrid {{\n\t\t\tbpm: {:.2},\n\t\t\toffset: {:.3},\n\t\t\tbeats_per_bar: {},\n\t\t\ttempo_changes: &[],\n\t\t}},\n\t}},",
+				estimate.bpm, estimate.offset, estimate.beats_per_bar
+			);
+			println!("\tmeasures: {},", estimate.measures);
+			continue;
+		}
+
+		let track = AnalysisTrack::analyse(&decoded.mono(), decoded.sample_rate);
+		let output = path.with_extension("analysis");
+		fs::write(&output, track.to_bytes()).unwrap();
+		println!(
+			"{} -> {}: {} frames, {} beats",
+			path.display(),
+			output.display(),
+			track.spectra.len(),
+			track.beats.len()
+		);
+	}
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -1,6 +1,7 @@
+use model::{key_pressed, mouse_pressed, update, view, Model};
+#[cfg(target_arch = "wasm32")]
 use {
 	async_std::task::block_on,
-	model::{mouse_pressed, update, view, Model},
 	nannou::{
 		prelude::*,
 		wgpu::{Backends, DeviceDescriptor, Limits},
@@ -9,10 +10,20 @@ use {
 	wasm_bindgen::prelude::*,
 };
 
+pub mod analysis;
+mod assets;
+pub mod audio;
+mod midi;
 mod model;
+mod notes;
+mod playback;
+mod reader;
+mod reload;
 mod scenes;
-// mod notes;
+pub mod tracks;
+mod transport;
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub async fn main_web() {
 	#[cfg(debug_assertions)]
@@ -44,6 +55,7 @@ pub async fn main_web() {
 				app.new_window()
 					.device_descriptor(device_desc)
 					.size(width as u32, height as u32)
+					.key_pressed(key_pressed)
 					.mouse_pressed(mouse_pressed)
 					.view(view)
 					.build_async()
@@ -58,3 +70,19 @@ pub async fn main_web() {
 		.await;
 	});
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn main_native() {
+	nannou::app(|app| {
+		app.new_window()
+			.size(800, 800)
+			.key_pressed(key_pressed)
+			.mouse_pressed(mouse_pressed)
+			.view(view)
+			.build()
+			.unwrap();
+		Model::new()
+	})
+	.update(update)
+	.run();
+}
@@ -0,0 +1,8 @@
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+	gender::main_native();
+}
+
+// The browser build starts from `gender::main_web` instead.
+#[cfg(target_arch = "wasm32")]
+fn main() {}
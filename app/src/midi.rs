@@ -0,0 +1,341 @@
+//! Notes of the arrangement a track was rendered from, read from a standard MIDI file served
+//! next to it if there is one, so scenes can follow what was actually played.
+
+use crate::reader::Reader;
+
+/// Tempo until the file sets one, in microseconds per quarter note.
+const DEFAULT_TEMPO: u32 = 500_000;
+
+/// A note, with times in seconds into the track.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Note {
+	pub start: f64,
+	pub end: f64,
+	pub pitch: u8,
+	/// From 0 to 1.
+	pub velocity: f32,
+	/// From 0 to 15.
+	pub channel: u8,
+}
+
+/// Every note of a track, in the order they start.
+pub struct Timeline {
+	pub notes: Vec<Note>,
+}
+
+impl Timeline {
+	/// Where the MIDI file of the track served at `path` is served.
+	pub fn path_for(path: &str) -> String {
+		let stem = path.rsplit_once('.').map_or(path, |(stem, _)| stem);
+		format!("{stem}.mid")
+	}
+
+	/// Reads a format 0 or 1 standard MIDI file. The time the file starts at is the start of the
+	/// track.
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, &'static str> {
+		let mut reader = Reader(bytes);
+		if reader.take(4)? != b"MThd" {
+			return Err("not a MIDI file");
+		}
+		let mut header = Reader(reader.chunk()?);
+		let format = u16::from_be_bytes(header.array()?);
+		let _tracks = u16::from_be_bytes(header.array()?);
+		let division = u16::from_be_bytes(header.array()?);
+		if format > 1 {
+			return Err("only format 0 and 1 files are supported");
+		}
+
+		let mut tempos = vec![];
+		let mut messages = vec![];
+		while !reader.0.is_empty() {
+			let kind = reader.array::<4>()?;
+			let chunk = reader.chunk()?;
+			// Other chunks are allowed, and to be skipped.
+			if &kind == b"MTrk" {
+				read_track(Reader(chunk), &mut tempos, &mut messages)?;
+			}
+		}
+
+		let seconds = seconds_per_tick(division, tempos);
+		// Notes stop before any start on the same tick, so a repeated note isn't cut short.
+		messages.sort_by_key(|message: &Message| (message.tick, message.velocity > 0));
+		let mut held: Vec<Note> = vec![];
+		let mut notes = vec![];
+		for message in messages {
+			let time = seconds(message.tick);
+			let playing = held
+				.iter()
+				.position(|note| note.channel == message.channel && note.pitch == message.pitch);
+			if let Some(index) = playing.filter(|_| message.velocity == 0) {
+				let mut note = held.remove(index);
+				note.end = time;
+				notes.push(note);
+			} else if message.velocity > 0 {
+				held.push(Note {
+					start: time,
+					end: f64::INFINITY,
+					pitch: message.pitch,
+					velocity: message.velocity as f32 / 127.0,
+					channel: message.channel,
+				});
+			}
+		}
+		// Notes that never stop last to the end of the track.
+		notes.extend(held);
+		notes.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+		Ok(Timeline { notes })
+	}
+
+	/// Notes starting after `from` and up to `to`.
+	pub fn started(&self, from: f64, to: f64) -> &[Note] {
+		let first = self.notes.partition_point(|note| note.start <= from);
+		let last = self.notes.partition_point(|note| note.start <= to);
+		&self.notes[first..last.max(first)]
+	}
+
+	/// Notes sounding at `time`.
+	pub fn held(&self, time: f64) -> impl Iterator<Item = &Note> {
+		let started = self.notes.partition_point(|note| note.start <= time);
+		self.notes[..started]
+			.iter()
+			.filter(move |note| note.end > time)
+	}
+}
+
+/// A note starting, or stopping if its velocity is 0.
+struct Message {
+	tick: u64,
+	channel: u8,
+	pitch: u8,
+	velocity: u8,
+}
+
+fn read_track(
+	mut reader: Reader,
+	tempos: &mut Vec<(u64, u32)>,
+	messages: &mut Vec<Message>,
+) -> Result<(), &'static str> {
+	let mut tick = 0;
+	let mut running = None;
+	while !reader.0.is_empty() {
+		tick += reader.variable()?;
+		let status = match reader.0.first() {
+			Some(&status) if status >= 0x80 => {
+				reader.take(1)?;
+				status
+			}
+			// Running status: the message has the same status as the last one.
+			Some(_) => running.ok_or("data without a status")?,
+			None => return Err("truncated"),
+		};
+		match status {
+			0xff => {
+				running = None;
+				let kind = reader.take(1)?[0];
+				let length = reader.variable()? as usize;
+				let data = reader.take(length)?;
+				if kind == 0x51 && data.len() == 3 {
+					tempos.push((tick, u32::from_be_bytes([0, data[0], data[1], data[2]])));
+				}
+			}
+			0xf0 | 0xf7 => {
+				running = None;
+				let length = reader.variable()? as usize;
+				reader.take(length)?;
+			}
+			0xf1..=0xfe => return Err("unexpected system message"),
+			_ => {
+				running = Some(status);
+				let data = reader.take(data_length(status))?;
+				let channel = status & 0x0f;
+				match status & 0xf0 {
+					0x80 => messages.push(Message {
+						tick,
+						channel,
+						pitch: data[0],
+						velocity: 0,
+					}),
+					0x90 => messages.push(Message {
+						tick,
+						channel,
+						pitch: data[0],
+						velocity: data[1],
+					}),
+					_ => {}
+				}
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Data bytes a channel message with `status` has.
+fn data_length(status: u8) -> usize {
+	match status & 0xf0 {
+		0xc0 | 0xd0 => 1,
+		_ => 2,
+	}
+}
+
+/// Converts ticks to seconds, following the tempo changes of every track.
+fn seconds_per_tick(division: u16, mut tempos: Vec<(u64, u32)>) -> impl Fn(u64) -> f64 {
+	tempos.sort_by_key(|&(tick, _)| tick);
+	// Seconds at each tempo change, and the seconds per tick from there.
+	let mut changes = vec![(0, 0.0, DEFAULT_TEMPO)];
+	for (tick, tempo) in tempos {
+		let &(last_tick, last_seconds, last_tempo) = changes.last().unwrap();
+		let seconds = last_seconds + (tick - last_tick) as f64 * per_tick(division, last_tempo);
+		changes.push((tick, seconds, tempo));
+	}
+
+	move |tick| {
+		let index = changes.partition_point(|&(change, _, _)| change <= tick) - 1;
+		let (change, seconds, tempo) = changes[index];
+		seconds + (tick - change) as f64 * per_tick(division, tempo)
+	}
+}
+
+/// Seconds per tick at `tempo` microseconds per quarter note. Timecode divisions don't depend on
+/// the tempo.
+fn per_tick(division: u16, tempo: u32) -> f64 {
+	if division & 0x8000 == 0 {
+		tempo as f64 / 1_000_000.0 / division.max(1) as f64
+	} else {
+		let frames = -((division >> 8) as u8 as i8) as f64;
+		let ticks = (division & 0xff).max(1) as f64;
+		1.0 / (frames * ticks)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// End of track.
+	const END: [u8; 4] = [0x00, 0xff, 0x2f, 0x00];
+
+	/// A standard MIDI file with a track chunk of each of `tracks`' events.
+	fn file(format: u16, division: u16, tracks: &[&[u8]]) -> Vec<u8> {
+		let mut bytes = b"MThd".to_vec();
+		bytes.extend(6u32.to_be_bytes());
+		bytes.extend(format.to_be_bytes());
+		bytes.extend((tracks.len() as u16).to_be_bytes());
+		bytes.extend(division.to_be_bytes());
+		for track in tracks {
+			bytes.extend(b"MTrk");
+			bytes.extend((track.len() as u32 + END.len() as u32).to_be_bytes());
+			bytes.extend(*track);
+			bytes.extend(END);
+		}
+		bytes
+	}
+
+	fn note(start: f64, end: f64, pitch: u8, velocity: u8, channel: u8) -> Note {
+		Note {
+			start,
+			end,
+			pitch,
+			velocity: velocity as f32 / 127.0,
+			channel,
+		}
+	}
+
+	#[test]
+	fn running_status_repeats_the_last_status() {
+		// 96 ticks a quarter note at 120 BPM is half a second.
+		let bytes = file(
+			0,
+			96,
+			&[&[
+				0x00, 0x90, 60, 100, // Note on.
+				0x60, 60, 0, // Running status, with velocity 0 to stop it.
+				0x00, 62, 127, // Running status, to start another.
+				0x60, 0x80, 62, 64, // Note off.
+			]],
+		);
+		let timeline = Timeline::from_bytes(&bytes).unwrap();
+		assert_eq!(
+			timeline.notes,
+			[note(0.0, 0.5, 60, 100, 0), note(0.5, 1.0, 62, 127, 0)]
+		);
+		assert_eq!(timeline.started(0.0, 0.5), &timeline.notes[1..]);
+		assert_eq!(timeline.held(0.25).count(), 1);
+	}
+
+	#[test]
+	fn tempo_changes_time_every_track() {
+		let tempos = [
+			0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20, // 120 BPM.
+			0x60, 0xff, 0x51, 0x03, 0x0f, 0x42, 0x40, // 60 BPM from the second beat.
+		];
+		// The third beat and the fourth, on channel 2.
+		let notes = [0x81, 0x40, 0x91, 64, 100, 0x60, 0x81, 64, 0];
+		let bytes = file(1, 96, &[&tempos, &notes]);
+		assert_eq!(
+			Timeline::from_bytes(&bytes).unwrap().notes,
+			[note(1.5, 2.5, 64, 100, 1)]
+		);
+	}
+
+	#[test]
+	fn timecode_divisions_ignore_the_tempo() {
+		// 25 frames a second of 40 ticks, so a millisecond a tick, whatever the tempo.
+		let division = ((-25i8 as u8 as u16) << 8) | 40;
+		let events = [
+			0x00, 0xff, 0x51, 0x03, 0x0f, 0x42, 0x40, // 60 BPM.
+			0x87, 0x68, 0x90, 60, 100, // 1000 ticks.
+			0x83, 0x74, 0x80, 60, 0, // 500 more.
+		];
+		let bytes = file(0, division, &[&events]);
+		assert_eq!(
+			Timeline::from_bytes(&bytes).unwrap().notes,
+			[note(1.0, 1.5, 60, 100, 0)]
+		);
+	}
+
+	#[test]
+	fn notes_that_never_stop_last_to_the_end() {
+		let bytes = file(0, 96, &[&[0x00, 0x90, 60, 100]]);
+		let timeline = Timeline::from_bytes(&bytes).unwrap();
+		assert_eq!(timeline.notes, [note(0.0, f64::INFINITY, 60, 100, 0)]);
+	}
+
+	#[test]
+	fn truncated_files_are_errors() {
+		let bytes = file(0, 96, &[&[0x00, 0x90, 60, 100, 0x60, 60, 0]]);
+		// A header on its own is a file without tracks.
+		let header = 14;
+		for length in (0..bytes.len()).filter(|&length| length != header) {
+			assert!(
+				Timeline::from_bytes(&bytes[..length]).is_err(),
+				"{length} bytes"
+			);
+		}
+		let empty = Timeline::from_bytes(&bytes[..header]).unwrap();
+		assert!(empty.notes.is_empty());
+
+		// Chunks that end in the middle of an event.
+		let mut bytes = file(0, 96, &[]);
+		bytes.extend(b"MTrk\0\0\0\x03\x00\x90\x3c");
+		assert_eq!(Timeline::from_bytes(&bytes).err(), Some("truncated"));
+	}
+
+	#[test]
+	fn malformed_files_are_errors() {
+		let error = |bytes: &[u8]| Timeline::from_bytes(bytes).err();
+		assert_eq!(error(b"RIFF"), Some("not a MIDI file"));
+		assert_eq!(
+			error(&file(2, 96, &[])),
+			Some("only format 0 and 1 files are supported")
+		);
+		assert_eq!(
+			error(&file(0, 96, &[&[0x00, 60, 100]])),
+			Some("data without a status")
+		);
+		assert_eq!(
+			error(&file(0, 96, &[&[0x00, 0xf1, 0x00]])),
+			Some("unexpected system message")
+		);
+	}
+}
@@ -1,5 +1,16 @@
 use {
-	crate::scenes::*,
+	crate::{
+		analysis::{AnalysisTrack, PITCH_CLASSES},
+		assets::Asset,
+		audio::{self, AudioSource, TrackId},
+		midi::Timeline,
+		notes::Pattern,
+		playback::{Playback, State},
+		reload::Changes,
+		scenes::*,
+		tracks::BeatGrid,
+		transport::Event,
+	},
 	nannou::{
 		noise::{
 			utils::{NoiseMap, NoiseMapBuilder, PlaneMapBuilder},
@@ -7,61 +18,77 @@ use {
 		},
 		prelude::*,
 	},
-	web_sys::{AnalyserNode, AudioContext, HtmlAudioElement},
+	std::{cell::Ref, collections::HashMap, ops::Range, ptr, rc::Rc},
 };
 
 pub const CANVAS_WIDTH: usize = 20;
 pub const CANVAS_HEIGHT: usize = 20;
 const SCALING: usize = 40;
-
-pub struct Audio {
-	pub analyser: AnalyserNode,
-	context: AudioContext,
-	elements: [HtmlAudioElement; 2],
-	current_element: usize,
-}
+/// Keys that jump to the first section, the second and so on.
+const SECTION_KEYS: [Key; 9] = [
+	Key::Key1,
+	Key::Key2,
+	Key::Key3,
+	Key::Key4,
+	Key::Key5,
+	Key::Key6,
+	Key::Key7,
+	Key::Key8,
+	Key::Key9,
+];
 
 pub struct Model {
 	pub noise_matrix: RidgedMulti,
 	pub noisemap: NoiseMap,
 	pub scaling: usize,
-	pub audio: Audio,
+	pub audio: Box<dyn AudioSource>,
+	playback: Playback,
+	/// What the live analyser was last set up with.
+	fft_size: usize,
+	analyses: HashMap<String, Asset<AnalysisTrack>>,
+	patterns: HashMap<String, Asset<Pattern>>,
+	timelines: HashMap<String, Asset<Option<Timeline>>>,
+	/// The scenes built in, and which of them play.
+	registry: Registry,
+	/// The scene list from the server, until it has been played or failed to load.
+	definitions: Option<Asset<Vec<Rc<Scene>>>>,
+	/// Files on the server that changed, to load again.
+	changes: Changes,
+	/// Why the scene list or one of its tracks didn't load, if one didn't. The compiled scenes play
+	/// instead.
+	scene_error: Option<String>,
 
-	scenes: Vec<&'static Scene>,
-	queue_next: bool,
-	current_scene: usize,
-	current_measure: usize,
 	pub last_whole: f64,
+	/// Bars since the first downbeat.
+	pub last_bar: i64,
+	/// Position in the current bar, in beats.
+	pub last_beat: f64,
+	/// This frame's, kept until the next one to tell what changed.
+	pub modifiers: Modifiers,
+}
+
+/// Where in the album the viewer is.
+pub struct Location {
+	/// The section playing, if the scene playing is in one.
+	pub section: Option<&'static Section>,
+	/// The scene's place in the section, from 0.
+	pub scene: usize,
+	/// How many scenes the section has.
+	pub scenes: usize,
 }
 
 impl Model {
 	pub fn new() -> Self {
-		let mut scenes = vec![];
-		scenes.extend_from_slice(for_against::SCENES);
-		scenes.extend_from_slice(terra_firmament::SCENES);
-		let context = AudioContext::new().unwrap();
-		let analyser = AnalyserNode::new(&context).unwrap();
-		analyser.set_fft_size(32);
-		let element1 = HtmlAudioElement::new_with_src(scenes[0].path).unwrap();
-		let element2 = HtmlAudioElement::new_with_src(scenes[1].path).unwrap();
-
-		let _ = context
-			.create_media_element_source(&element1)
-			.unwrap()
-			.connect_with_audio_node(&analyser);
-		let _ = context
-			.create_media_element_source(&element2)
-			.unwrap()
-			.connect_with_audio_node(&analyser);
-		let _ = analyser.connect_with_audio_node(&context.destination());
-
-		let audio = Audio {
-			analyser,
-			context,
-			elements: [element1, element2],
-			current_element: 0,
-		};
-		Model {
+		Self::with_audio(audio::platform())
+	}
+
+	pub fn with_audio(mut audio: Box<dyn AudioSource>) -> Self {
+		let registry = Registry::from_environment();
+		let definitions = load_definitions(&registry);
+		let playback = Playback::new(registry.scenes(), &mut *audio);
+		let fft_size = playback.scene().bands().fft_size;
+		audio.set_fft_size(fft_size);
+		let mut model = Model {
 			scaling: SCALING,
 			noise_matrix: RidgedMulti::new(),
 			noisemap: PlaneMapBuilder::new(&RidgedMulti::new())
@@ -69,93 +96,322 @@ impl Model {
 				.set_x_bounds(-10.0, 10.0)
 				.set_y_bounds(-10.0, 10.0)
 				.build(),
-			scenes,
-			queue_next: false,
-			current_scene: 0,
-			current_measure: 0,
 			audio,
+			playback,
+			fft_size,
+			analyses: HashMap::new(),
+			patterns: HashMap::new(),
+			timelines: HashMap::new(),
+			registry,
+			definitions: Some(definitions),
+			changes: Changes::watch(),
+			scene_error: None,
 			last_whole: 0.0,
+			last_bar: 0,
+			last_beat: 0.0,
+			modifiers: Modifiers::default(),
+		};
+		model.load_assets();
+		model
+	}
+
+	/// Starts loading whatever the scenes need alongside their tracks that isn't loading yet.
+	fn load_assets(&mut self) {
+		for scene in self.playback.scenes() {
+			self.analyses
+				.entry(scene.path.to_string())
+				.or_insert_with(|| {
+					Asset::load(
+						&AnalysisTrack::path_for(&scene.path),
+						AnalysisTrack::from_bytes,
+					)
+				});
+			self.timelines
+				.entry(scene.path.to_string())
+				.or_insert_with(|| {
+					Asset::load_optional(&Timeline::path_for(&scene.path), Timeline::from_bytes)
+				});
+			if let Some(path) = &scene.pattern {
+				(self.patterns.entry(path.to_string()))
+					.or_insert_with(|| Asset::load(path, Pattern::from_bytes));
+			}
 		}
 	}
 
-	pub fn current_scene(&self) -> &Scene {
-		self.scenes[self.current_scene]
+	/// Loads the files that changed on the server again: the scene list, patterns, and tracks'
+	/// analyses and MIDI files. Tracks themselves are only loaded once.
+	fn reload(&mut self) {
+		for path in self.changes.take() {
+			if path == SCENES_PATH {
+				self.definitions = Some(load_definitions(&self.registry));
+			}
+			if let Some(pattern) = self.patterns.get_mut(path.as_str()) {
+				*pattern = Asset::load(&path, Pattern::from_bytes);
+			}
+			for (scene, analysis) in &mut self.analyses {
+				if AnalysisTrack::path_for(scene) == path {
+					*analysis = Asset::load(&path, AnalysisTrack::from_bytes);
+				}
+			}
+			for (scene, timeline) in &mut self.timelines {
+				if Timeline::path_for(scene) == path {
+					*timeline = Asset::load_optional(&path, Timeline::from_bytes);
+				}
+			}
+		}
 	}
 
-	fn play(&self) {
-		let _ = self.audio.context.resume();
-		let element = &self.audio.elements[self.audio.current_element];
-		if element.ready_state() != 4 {
+	/// Plays the scene list from the server instead of the compiled scenes once it arrives. If
+	/// the show has started, it carries on from the same place, see `Playback::reload_scenes`.
+	fn swap_definitions(&mut self) {
+		let Some(definitions) = &self.definitions else {
+			return;
+		};
+		if let Some(error) = definitions.error() {
+			self.scene_error = Some(error);
+			self.definitions = None;
 			return;
 		}
-		if element.paused() {
-			element.set_loop(self.scenes[self.current_scene].loop_);
-			let _ = element.play();
+		let Some(scenes) = definitions.get().map(|scenes| scenes.clone()) else {
+			return;
+		};
+		let audio = &mut *self.audio;
+		match self.playback.state() {
+			State::Loading | State::Ready | State::Finished => {
+				self.playback.replace_scenes(scenes, audio)
+			}
+			_ => {
+				if !self.playback.reload_scenes(scenes, audio) {
+					return;
+				}
+			}
 		}
+		self.definitions = None;
+		self.scene_error = None;
+		self.load_assets();
 	}
 
-	fn stop(&self) {
-		let _ = self.audio.elements[self.audio.current_element].pause();
+	/// Goes back to the compiled scenes if a track of the scene list fails to load, and shows why.
+	fn check_tracks(&mut self) {
+		let Some(error) = self.playback.error(&*self.audio) else {
+			return;
+		};
+		self.scene_error = Some(error);
+		let compiled = self.registry.scenes();
+		let playing = self.playback.scenes();
+		let on_compiled = compiled.len() == playing.len()
+			&& (compiled.iter().zip(playing)).all(|(a, b)| Rc::ptr_eq(a, b));
+		if !on_compiled {
+			self.playback.replace_scenes(compiled, &mut *self.audio);
+			self.load_assets();
+		}
 	}
-}
 
-pub fn update(_app: &App, model: &mut Model, _update: Update) {
-	let scene = &model.scenes[model.current_scene];
-	let element = &model.audio.elements[model.audio.current_element];
-	let measure_duration = element.duration() / scene.measures as f64;
-	let mut last_whole = (element.current_time() % measure_duration) / measure_duration;
-	if last_whole < model.last_whole {
-		model.current_measure += 1;
-
-		if model.current_measure == scene.measures {
-			model.current_measure = 0;
-			if !scene.loop_ {
-				model.queue_next = true;
+	/// Why the scene list or one of its tracks didn't load, if one didn't.
+	pub fn scene_error(&self) -> Option<&str> {
+		self.scene_error.as_deref()
+	}
+
+	pub fn current_scene(&self) -> &Scene {
+		self.playback.scene()
+	}
+
+	/// The list of scenes in runs of the same section. Scenes from the scene list that aren't
+	/// compiled into a section belong to the one before them.
+	fn sections(&self) -> Vec<(Option<&'static Section>, Range<usize>)> {
+		let mut runs: Vec<(Option<&'static Section>, Range<usize>)> = vec![];
+		for (index, scene) in self.playback.scenes().iter().enumerate() {
+			let previous = runs.last().and_then(|(section, _)| *section);
+			let section = self.registry.section_of(scene).or(previous);
+			match runs.last_mut() {
+				Some((last, range)) if same_section(*last, section) => range.end = index + 1,
+				_ => runs.push((section, index..index + 1)),
 			}
 		}
+		runs
+	}
+
+	/// Where the scene playing sits, `None` if the list is somehow empty.
+	pub fn location(&self) -> Option<Location> {
+		let current = self.playback.current();
+		let (section, range) =
+			(self.sections().into_iter()).find(|(_, range)| range.contains(&current))?;
+		Some(Location {
+			section,
+			scene: current - range.start,
+			scenes: range.len(),
+		})
+	}
+
+	/// Moves on to the first scene of the `number`th section in the list, counting from 0, at the
+	/// end of the current measure.
+	pub fn jump_to_section(&mut self, number: usize) {
+		let start = (self.sections().into_iter())
+			.filter(|(section, _)| section.is_some())
+			.nth(number)
+			.map(|(_, range)| range.start);
+		if let Some(start) = start {
+			self.playback.queue(start);
+		}
+	}
 
-		if model.queue_next {
-			model.queue_next = false;
-
-			if model.current_scene < model.scenes.len() - 1 {
-				model.stop();
-				model.current_scene += 1;
-				model.current_measure = 0;
-
-				let element =
-					HtmlAudioElement::new_with_src(model.scenes[model.current_scene + 1].path)
-						.unwrap();
-				let _ = (model.audio.context)
-					.create_media_element_source(&element)
-					.unwrap()
-					.connect_with_audio_node(&model.audio.context.destination());
-
-				model.audio.elements[model.audio.current_element] = element;
-				model.audio.current_element = model.audio.current_element ^ 1;
-				model.play();
-				last_whole = 0.0;
+	fn track(&self) -> TrackId {
+		self.playback.track()
+	}
+
+	/// The spectrum scenes react to: the precomputed one when the current track has been
+	/// analysed, the live analyser otherwise.
+	pub fn frequency_data(&self, data: &mut [u8]) {
+		let analysis = self.analyses.get(&*self.current_scene().path);
+		match analysis.and_then(Asset::get) {
+			Some(analysis) if analysis.spectra.bins == data.len() => {
+				let time = self.audio.current_time(self.track());
+				data.copy_from_slice(analysis.spectra.at(time));
 			}
+			_ => self.audio.frequency_data(data),
+		}
+	}
+
+	/// Onset strength from the current track's analysis, if it has loaded.
+	pub fn onset(&self) -> Option<f32> {
+		let analysis = self.analyses.get(&*self.current_scene().path)?.get()?;
+		Some(analysis.onset_at(self.audio.current_time(self.track())))
+	}
+
+	/// The current scene's pattern, once it has loaded.
+	pub fn pattern(&self) -> Option<Ref<'_, Pattern>> {
+		self.patterns
+			.get(self.current_scene().pattern.as_deref()?)?
+			.get()
+	}
+
+	/// Why the current scene's pattern didn't load, if it didn't.
+	pub fn pattern_error(&self) -> Option<String> {
+		self.patterns
+			.get(self.current_scene().pattern.as_deref()?)?
+			.error()
+	}
+
+	/// The notes of the current track, if it has a MIDI file and it has loaded.
+	pub fn timeline(&self) -> Option<Ref<'_, Timeline>> {
+		let timeline = self.timelines.get(&*self.current_scene().path)?.get()?;
+		Ref::filter_map(timeline, Option::as_ref).ok()
+	}
+
+	/// Why the current track's MIDI file didn't load, if it has one and it didn't.
+	pub fn timeline_error(&self) -> Option<String> {
+		self.timelines.get(&*self.current_scene().path)?.error()
+	}
+
+	/// Playback position in the current track, in seconds.
+	pub fn current_time(&self) -> f64 {
+		self.audio.current_time(self.track())
+	}
+
+	/// Ticks passed since the last frame.
+	pub fn ticks(&self) -> &[Event] {
+		self.playback.ticks()
+	}
+
+	pub fn grid(&self) -> BeatGrid {
+		self.playback.grid(&*self.audio)
+	}
+
+	/// Moves playback along, sets the analyser up for the scene now playing and updates the
+	/// position in the bar scenes are drawn at.
+	pub fn tick(&mut self) {
+		self.reload();
+		self.swap_definitions();
+		self.check_tracks();
+		self.playback.update(&mut *self.audio);
+		let fft_size = self.current_scene().bands().fft_size;
+		if fft_size != self.fft_size {
+			self.audio.set_fft_size(fft_size);
+			self.fft_size = fft_size;
 		}
+		let position = self.playback.position();
+		self.last_bar = position.bar;
+		self.last_beat = position.beat;
+		self.last_whole = position.beat / self.grid().beats_per_bar as f64;
+	}
+}
+
+fn same_section(a: Option<&Section>, b: Option<&Section>) -> bool {
+	match (a, b) {
+		(Some(a), Some(b)) => ptr::eq(a, b),
+		(a, b) => a.is_none() && b.is_none(),
 	}
+}
 
-	model.last_whole = last_whole;
+/// Starts loading the scene list from the server, leaving out compiled scenes of sections that
+/// don't play.
+fn load_definitions(registry: &Registry) -> Asset<Vec<Rc<Scene>>> {
+	let registry = registry.clone();
+	Asset::load(SCENES_PATH, move |bytes| {
+		let mut scenes = parse_scenes(bytes, &registry.compiled())?;
+		scenes.retain(|scene| registry.plays(scene));
+		if scenes.is_empty() {
+			return Err("none of the scenes are in sections that play".to_owned());
+		}
+		Ok(scenes)
+	})
+}
+
+pub fn update(app: &App, model: &mut Model, update: Update) {
+	model.tick();
+	model.modifiers = get_modifiers(
+		model,
+		app.window_rect(),
+		app.mouse.position(),
+		update.since_last.as_secs_f32(),
+	);
 }
 
 pub fn view(app: &App, model: &Model, frame: Frame) {
 	let draw = app.draw();
 	draw.background().color(BLACK);
-	let modifiers = get_modifiers(model);
+	let modifiers = &model.modifiers;
 
 	// debug info
 	{
+		let scene = model.current_scene();
+		let title = if scene.title.is_empty() {
+			&scene.path
+		} else {
+			&scene.title
+		};
+		let place = model.location().map_or(String::new(), |location| {
+			let section = location.section.map_or(String::new(), |section| {
+				format!("{} ({}) / ", section.title, section.words.join(" | "))
+			});
+			format!("{section}{}/{} ", location.scene + 1, location.scenes)
+		});
+		draw.text(&format!("{} / {place}{title}", ALBUM.title))
+			.x_y(-200.0, 240.0)
+			.w(400.0);
 		draw.text(&format!("Fps: {:?}", app.fps().round()))
 			.x_y(-200.0, 220.0);
+		draw.text(&format!("{:?}", model.playback.state()))
+			.x_y(-200.0, 200.0);
 
-		let [whole, half, quarter, eighth, sixteenth, bass, mid, treble] = modifiers;
+		let Modifiers {
+			whole,
+			half,
+			quarter,
+			eighth,
+			sixteenth,
+			bass,
+			mid,
+			treble,
+			rms,
+			centroid,
+			flux,
+			onsets,
+			..
+		} = *modifiers;
 
 		draw.text(&format!(
-			"1({:.2}) 2({half:.2}) 4({quarter:.2}) 8({eighth:.2}) 16({sixteenth:.2})",
-			model.last_whole
+			"1({whole:.2}) 2({half:.2}) 4({quarter:.2}) 8({eighth:.2}) 16({sixteenth:.2})"
 		))
 		.x_y(-200.0, 180.0);
 
@@ -164,22 +420,42 @@ pub fn view(app: &App, model: &Model, frame: Frame) {
 		))
 		.x_y(-200.0, 160.0);
 
-		let mut fft = [0; 16];
-		model.audio.analyser.get_byte_frequency_data(&mut fft);
-		let fft = fft
-			.iter()
-			.map(|&value| format!("{:.2}|", value as f32 / 255.0))
+		draw.text(&format!(
+			"rms({rms:.2}) centroid({centroid:.2}) flux({flux:.2}) onsets({onsets:.2})"
+		))
+		.x_y(-200.0, 140.0);
+
+		let pitch_class = modifiers
+			.pitch_class
+			.map_or("-", |class| PITCH_CLASSES[class]);
+		draw.text(&format!(
+			"pitch class({pitch_class}) balance({:.2}) width({:.2})",
+			modifiers.balance, modifiers.width
+		))
+		.x_y(-200.0, 120.0);
+
+		let errors = [
+			model.scene_error().map(str::to_owned),
+			model.pattern_error(),
+			model.timeline_error(),
+		];
+		for (error, y) in errors.into_iter().flatten().zip([100.0, 60.0, 20.0]) {
+			draw.text(&error).x_y(-200.0, y).w(400.0).color(RED);
+		}
+
+		let fft = (modifiers.spectrum.iter())
+			.map(|value| format!("{value:.2}|"))
 			.collect::<String>();
 		draw.text(&format!("FFT: {fft}")).x_y(200.0, 160.0);
 	}
 
-	if model.audio.elements[model.audio.current_element].ready_state() != 4 {
+	if !model.audio.ready(model.track()) {
 		return;
 	}
 
 	for i in 0..CANVAS_WIDTH {
 		for j in 0..CANVAS_HEIGHT {
-			(model.scenes[model.current_scene].render)(
+			(model.current_scene().render)(
 				&draw,
 				model,
 				i,
@@ -193,10 +469,183 @@ pub fn view(app: &App, model: &Model, frame: Frame) {
 	draw.to_frame(app, &frame).unwrap();
 }
 
+/// Number keys jump to a section and the right arrow moves on to the next scene, both at the end
+/// of the current measure.
+pub fn key_pressed(_app: &App, model: &mut Model, key: Key) {
+	if let Some(number) = SECTION_KEYS
+		.iter()
+		.position(|&section_key| section_key == key)
+	{
+		model.jump_to_section(number);
+	}
+	if key == Key::Right {
+		model.playback.queue_next();
+	}
+}
+
 pub fn mouse_pressed(app: &App, model: &mut Model, _button: MouseButton) {
-	model.play();
+	model.playback.start(&mut *model.audio);
 
-	if model.scenes[model.current_scene].boxes[0].inside(app.mouse.x, app.mouse.y) {
-		model.queue_next = true;
+	let boxes = &model.current_scene().boxes;
+	if (boxes.first()).is_some_and(|area| area.inside(app.mouse.x, app.mouse.y)) {
+		model.playback.queue_next();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{
+			analysis::{Bands, Envelope, Follower, CHROMA_SIZE},
+			audio::{Call, MockAudio},
+		},
+		std::{borrow::Cow, f32::consts::TAU},
+	};
+
+	/// A model playing one looping scene of four bars over 8 seconds, 120 BPM, with the audio
+	/// it plays through. The scene list from the server is left out.
+	fn model() -> (Model, MockAudio) {
+		let audio = MockAudio::new().with_track("/test.mp3", 8.0);
+		let mut model = Model::with_audio(Box::new(audio.clone()));
+		let scene = Scene {
+			path: Cow::Borrowed("/test.mp3"),
+			loop_: true,
+			measures: 4,
+			bands: Some(Cow::Owned(Bands {
+				fft_size: 64,
+				..Bands::DEFAULT
+			})),
+			envelopes: Cow::Borrowed(&[(
+				Cow::Borrowed("bass"),
+				Envelope {
+					attack: 0.0,
+					..Envelope::DEFAULT
+				},
+			)]),
+			..for_against::SCENES[0].clone()
+		};
+		model.definitions = None;
+		model.playback = Playback::new(vec![Rc::new(scene)], &mut *model.audio);
+		model.load_assets();
+		(model, audio)
+	}
+
+	/// Ticks through `seconds` of frames at 60 fps.
+	fn run(model: &mut Model, audio: &MockAudio, seconds: f64) {
+		for _ in 0..(seconds * 60.0).round() as usize {
+			audio.advance(1.0 / 60.0);
+			model.tick();
+		}
+	}
+
+	fn read(model: &Model) -> Modifiers {
+		let window = Rect::from_w_h(800.0, 800.0);
+		get_modifiers(model, window, pt2(0.0, 0.0), 1.0 / 60.0)
+	}
+
+	#[test]
+	fn tick_follows_the_clock() {
+		let (mut model, audio) = model();
+		model.tick();
+		assert!(audio.calls().contains(&Call::SetFftSize(64)));
+		model.playback.start(&mut *model.audio);
+
+		run(&mut model, &audio, 3.25);
+		assert_eq!(model.last_bar, 1);
+		assert!((model.last_beat - 2.5).abs() < 1e-6, "{}", model.last_beat);
+		assert!(
+			(model.last_whole - 0.625).abs() < 1e-6,
+			"{}",
+			model.last_whole
+		);
+		assert_eq!(model.location().map(|location| location.scene), Some(0));
+
+		// A whole pass of the track later it has looped back to the same place.
+		run(&mut model, &audio, 8.0);
+		assert_eq!(model.playback.position().loops, 1);
+		assert_eq!(model.last_bar, 1);
+		assert!((model.last_beat - 2.5).abs() < 1e-6, "{}", model.last_beat);
+	}
+
+	#[test]
+	fn falls_back_to_the_compiled_scenes_when_a_track_fails() {
+		let (mut model, audio) = model();
+		audio.fail(model.track(), "/test.mp3: not found");
+		model.tick();
+		assert_eq!(model.scene_error(), Some("/test.mp3: not found"));
+		let compiled = model.registry.scenes();
+		assert!(Rc::ptr_eq(
+			model.playback.scenes().first().unwrap(),
+			&compiled[0]
+		));
+		assert_eq!(model.playback.scenes().len(), compiled.len());
+	}
+
+	#[test]
+	fn modifiers_read_the_scripted_spectrum() {
+		let (mut model, audio) = model();
+		model.tick();
+		model.playback.start(&mut *model.audio);
+		run(&mut model, &audio, 0.75);
+
+		// Only the bass bins of the 32 sound, and only on the left.
+		let mut left = [0; 32];
+		left[..4].fill(255);
+		audio.set_spectrum(&left);
+		audio.set_channel_spectra(&left, &[0; 32]);
+		let modifiers = read(&model);
+		assert_eq!(modifiers.spectrum.len(), 32);
+		assert_eq!(modifiers.bass, 1.0);
+		assert_eq!((modifiers.mid, modifiers.treble), (0.0, 0.0));
+		assert_eq!(modifiers.balance, -1.0);
+		assert_eq!(modifiers.pan("bass"), -1.0);
+		assert_eq!(modifiers.envelope("bass").map(Follower::value), Some(1.0));
+
+		// Beats into the bar, at 1.5 beats in.
+		assert!(
+			(modifiers.quarter - 0.5).abs() < 1e-4,
+			"{}",
+			modifiers.quarter
+		);
+		assert!((modifiers.half - 0.75).abs() < 1e-4, "{}", modifiers.half);
+
+		// Silence lets the follower go, and the spectrum got no louder.
+		audio.set_spectrum(&[0; 32]);
+		audio.set_channel_spectra(&[0; 32], &[0; 32]);
+		model.modifiers = modifiers;
+		let modifiers = read(&model);
+		assert_eq!(modifiers.bass, 0.0);
+		assert_eq!(modifiers.flux, 0.0);
+		let bass = modifiers.envelope("bass").map(Follower::value).unwrap();
+		assert!(0.0 < bass && bass < 1.0, "{bass}");
+
+		// Half bars follow the meter: 4.5 beats into a bar of 6 is half way through the second.
+		model.last_beat = 4.5;
+		model.last_whole = 0.75;
+		assert_eq!(read(&model).half, 0.5);
+	}
+
+	#[test]
+	fn scenes_read_the_samples_they_ask_for() {
+		let (mut model, audio) = model();
+		let tone = (0..CHROMA_SIZE)
+			.map(|n| (TAU * 440.0 * n as f32 / audio.sample_rate()).sin())
+			.collect::<Vec<_>>();
+		audio.set_waveform(&tone);
+		audio.set_spectrum(&[255; 32]);
+		let modifiers = read(&model);
+		assert!(modifiers.waveform.is_empty());
+		assert_eq!((modifiers.chroma, modifiers.pitch_class), ([0.0; 12], None));
+
+		let scene = Scene {
+			waveform: 4,
+			chroma: true,
+			..model.current_scene().clone()
+		};
+		model.playback = Playback::new(vec![Rc::new(scene)], &mut *model.audio);
+		let modifiers = read(&model);
+		assert_eq!(modifiers.waveform, tone[CHROMA_SIZE - 4..]);
+		assert_eq!(modifiers.pitch_class, Some(9));
 	}
 }
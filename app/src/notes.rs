@@ -1,44 +1,217 @@
-// use crate::model::EFFECTS;
-
-#[allow(non_upper_case_globals)]
-const o: bool = false;
-const X: bool = true;
-
-pub const NOTES: &[[[bool; 4]; 16]] = &[
-	[
-		[X, X, o, X], //
-		[o, o, o, X],
-		[o, o, o, X],
-		[o, o, o, X],
-		[o, o, X, X], //
-		[o, o, o, X],
-		[o, o, o, X],
-		[o, o, o, X],
-		[o, X, o, X], //
-		[o, o, o, X],
-		[o, o, X, X],
-		[o, o, o, X],
-		[o, o, o, X], //
-		[o, o, o, X],
-		[o, o, o, X],
-		[o, o, o, X],
-	],
-	[
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-		[o, o, o, o],
-	],
-];
+//! Step sequencer patterns scenes can hit in time with. They are written as text and served
+//! under `static/`, one lane per line and one sixteenth note per step:
+//!
+//! ```text
+//! # Lines starting with a hash are comments.
+//! kick:  x...|....|x...|....
+//! snare: ....|x...|....|x...
+//! hat:   x3x3|x3x3
+//! ```
+//!
+//! `x` hits at full velocity, `1` to `9` at that many ninths of it, and `.` rests. Spaces and
+//! `|` are only there to make the steps easier to count. Lanes are named before a colon, or
+//! numbered from 0 if they aren't, and each loops on its own length.
+
+use std::fmt::{self, Display, Formatter};
+
+/// One line of a pattern.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lane {
+	pub name: String,
+	/// Velocity of each step, from 0 for a rest to 1.
+	pub steps: Vec<f32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pattern {
+	pub lanes: Vec<Lane>,
+}
+
+/// Where a pattern stopped making sense, counting lines and columns from 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+	pub line: usize,
+	pub column: usize,
+	pub message: String,
+}
+
+impl Display for ParseError {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		write!(
+			f,
+			"line {}, column {}: {}",
+			self.line, self.column, self.message
+		)
+	}
+}
+
+impl Pattern {
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+		let text = std::str::from_utf8(bytes).map_err(|error| {
+			let before = &bytes[..error.valid_up_to()];
+			let line = before.iter().filter(|&&byte| byte == b'\n').count() + 1;
+			let start = before
+				.iter()
+				.rposition(|&byte| byte == b'\n')
+				.map_or(0, |i| i + 1);
+			ParseError {
+				line,
+				column: String::from_utf8_lossy(&before[start..]).chars().count() + 1,
+				message: "not UTF-8".to_owned(),
+			}
+		})?;
+		Self::parse(text)
+	}
+
+	pub fn parse(text: &str) -> Result<Self, ParseError> {
+		let mut lanes: Vec<Lane> = vec![];
+		for (index, line) in text.lines().enumerate() {
+			let error = |column: usize, message: String| ParseError {
+				line: index + 1,
+				column: column + 1,
+				message,
+			};
+			let content = line.trim_start();
+			if content.is_empty() || content.starts_with('#') {
+				continue;
+			}
+			let indent = line.chars().count() - content.chars().count();
+
+			let (name, steps, offset) = match content.split_once(':') {
+				Some((name, steps)) => {
+					let name = name.trim_end();
+					if name.is_empty() || name.contains(char::is_whitespace) {
+						return Err(error(indent, format!("bad lane name {name:?}")));
+					}
+					let before = &content[..content.len() - steps.len()];
+					(name.to_owned(), steps, indent + before.chars().count())
+				}
+				None => (lanes.len().to_string(), content, indent),
+			};
+			if lanes.iter().any(|lane| lane.name == name) {
+				return Err(error(indent, format!("lane {name:?} is already defined")));
+			}
+
+			let mut velocities = vec![];
+			for (column, character) in steps.chars().enumerate() {
+				match character {
+					'x' | 'X' => velocities.push(1.0),
+					'.' => velocities.push(0.0),
+					'1'..='9' => velocities.push(character.to_digit(10).unwrap() as f32 / 9.0),
+					'|' => {}
+					_ if character.is_whitespace() => {}
+					_ => {
+						return Err(error(
+							offset + column,
+							format!("expected a step, `x`, `1` to `9` or `.`, found {character:?}"),
+						))
+					}
+				}
+			}
+			if velocities.is_empty() {
+				return Err(error(indent, format!("lane {name:?} has no steps")));
+			}
+
+			lanes.push(Lane {
+				name,
+				steps: velocities,
+			});
+		}
+		Ok(Pattern { lanes })
+	}
+
+	/// Velocity of each lane `tick` sixteenths after the first downbeat.
+	pub fn step(&self, tick: i64) -> impl Iterator<Item = (&str, f32)> {
+		(self.lanes.iter()).map(move |lane| {
+			let step = tick.rem_euclid(lane.steps.len() as i64) as usize;
+			(lane.name.as_str(), lane.steps[step])
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const X: bool = true;
+	#[allow(non_upper_case_globals)]
+	const o: bool = false;
+
+	/// The pattern that was compiled in before patterns were served, bar by bar and step by
+	/// step, with a column per lane.
+	const NOTES: [[[bool; 4]; 16]; 2] = [
+		[
+			[X, X, o, X],
+			[o, o, o, X],
+			[o, o, o, X],
+			[o, o, o, X],
+			[o, o, X, X],
+			[o, o, o, X],
+			[o, o, o, X],
+			[o, o, o, X],
+			[o, X, o, X],
+			[o, o, o, X],
+			[o, o, X, X],
+			[o, o, o, X],
+			[o, o, o, X],
+			[o, o, o, X],
+			[o, o, o, X],
+			[o, o, o, X],
+		],
+		[[o; 4]; 16],
+	];
+
+	fn error(text: &str) -> (usize, usize) {
+		let error = Pattern::parse(text).unwrap_err();
+		(error.line, error.column)
+	}
+
+	#[test]
+	fn parses_lanes() {
+		let pattern = Pattern::parse("# comment\n\nkick: x.|9.\n  x3\n").unwrap();
+		assert_eq!(
+			pattern.lanes,
+			vec![
+				Lane {
+					name: "kick".to_owned(),
+					steps: vec![1.0, 0.0, 1.0, 0.0],
+				},
+				Lane {
+					name: "1".to_owned(),
+					steps: vec![1.0, 3.0 / 9.0],
+				},
+			]
+		);
+		let steps = |tick| pattern.step(tick).collect::<Vec<_>>();
+		assert_eq!(steps(5), vec![("kick", 0.0), ("1", 3.0 / 9.0)]);
+		assert_eq!(steps(-2), vec![("kick", 1.0), ("1", 1.0)]);
+	}
+
+	#[test]
+	fn reports_where_errors_are() {
+		assert_eq!(error("  kick snare: x"), (1, 3));
+		assert_eq!(error(": x"), (1, 1));
+		assert_eq!(error("a: x\n\n a: x"), (3, 2));
+		assert_eq!(error("kick: x.y."), (1, 9));
+		assert_eq!(error("x\n  xo"), (2, 4));
+		assert_eq!(error("# nothing\nkick: | |"), (2, 1));
+		let error = Pattern::from_bytes(b"a: x\nb: x\xff").unwrap_err();
+		assert_eq!((error.line, error.column), (2, 5));
+	}
+
+	#[test]
+	fn served_notes_match_the_compiled_ones() {
+		let pattern = Pattern::parse(include_str!("../../static/patterns/notes.txt")).unwrap();
+		let names = (pattern.lanes.iter())
+			.map(|lane| lane.name.as_str())
+			.collect::<Vec<_>>();
+		assert_eq!(names, ["flash", "back", "throw", "pulse"]);
+		for (tick, step) in NOTES.iter().flatten().enumerate() {
+			let velocities = pattern.step(tick as i64).map(|(_, velocity)| velocity);
+			let hits = velocities
+				.map(|velocity| velocity > 0.0)
+				.collect::<Vec<_>>();
+			assert_eq!(hits, step, "step {tick}");
+		}
+	}
+}
@@ -0,0 +1,676 @@
+//! Which scene is playing, and how playback gets from one scene to the next.
+
+use {
+	crate::{
+		audio::{AudioSource, Fade, TrackId},
+		scenes::{Scene, Transition},
+		tracks::{self, BeatGrid},
+		transport::{Boundary, Event, Position, Transport},
+	},
+	std::rc::Rc,
+};
+
+/// How long before the bar line a scene change is handed to the audio backend. It has to be
+/// longer than the gap between two frames.
+const LOOKAHEAD: f64 = 0.2;
+
+/// ```text
+/// Loading --first track ready--> Ready --start--> Playing --queue_next, queue--> Queued
+/// Queued --bar line--> Transitioning --next track ready--> Playing
+/// Queued --bar line, next scene shares the track--> Playing
+/// Queued --bar line, no scenes left--> Finished
+/// ```
+///
+/// The next scene is the one after the current one, unless `queue` picked another.
+///
+/// Scenes that don't loop queue the next one themselves once their measures are over. When the
+/// next track is ready in time, it is scheduled to start right on the bar line on the audio clock
+/// and `Transitioning` is skipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+	/// The first scene's track is still loading.
+	Loading,
+	/// Waiting for a user gesture, which browsers need before they play anything.
+	Ready,
+	Playing,
+	/// Moving on to the next scene at the end of the current measure.
+	Queued,
+	/// Waiting for the next scene's track to be ready to play.
+	Transitioning,
+	/// The last scene is over.
+	Finished,
+}
+
+pub struct Playback {
+	scenes: Vec<Rc<Scene>>,
+	/// The track of each scene, loaded up front. Scenes playing the same file share one.
+	tracks: Vec<TrackId>,
+	state: State,
+	current_scene: usize,
+	/// The scene to move on to instead of the one after the current one.
+	next_scene: Option<usize>,
+	current_measure: usize,
+	transport: Transport,
+	/// Audio clock time the next scene's track was scheduled to start at.
+	scheduled: Option<f64>,
+	/// Ticks the current scene passed in the last update.
+	ticks: Vec<Event>,
+}
+
+impl Playback {
+	pub fn new(scenes: Vec<Rc<Scene>>, audio: &mut dyn AudioSource) -> Self {
+		Self::with_loaded(scenes, audio, vec![])
+	}
+
+	/// Starts over with other scenes, without loading the tracks these ones have already loaded
+	/// again. Whatever is playing stops, and tracks kept start again from the top.
+	pub fn replace_scenes(&mut self, scenes: Vec<Rc<Scene>>, audio: &mut dyn AudioSource) {
+		for &track in &self.tracks {
+			audio.pause(track);
+			audio.fade(track, Fade::FULL);
+			if audio.ready(track) {
+				audio.seek(track, 0.0);
+			}
+		}
+		let loaded = self.loaded();
+		*self = Self::with_loaded(scenes, audio, loaded);
+	}
+
+	/// Swaps in other versions of the scenes while the show plays, keeping its place. The track
+	/// carries on as the scene playing it nearest the current place in the list, looping or not
+	/// as that scene says. If no scene plays it any more, it starts over like `replace_scenes`.
+	/// While a scene change is under way nothing changes and it returns false, to be tried again
+	/// once it is over.
+	pub fn reload_scenes(&mut self, scenes: Vec<Rc<Scene>>, audio: &mut dyn AudioSource) -> bool {
+		if self.state == State::Transitioning || self.scheduled.is_some() {
+			return false;
+		}
+		let current = (0..scenes.len())
+			.filter(|&index| scenes[index].path == self.scene().path)
+			.min_by_key(|&index| index.abs_diff(self.current_scene));
+		let Some(current) = current else {
+			self.replace_scenes(scenes, audio);
+			return true;
+		};
+
+		let (track, loop_) = (self.track(), scenes[current].loop_);
+		if loop_ != self.scene().loop_ && !audio.paused(track) {
+			audio.play(track, loop_);
+		}
+		let loaded = self.loaded();
+		self.tracks = load_tracks(&scenes, audio, loaded);
+		self.scenes = scenes;
+		self.current_scene = current;
+		self.next_scene = None;
+		self.current_measure %= self.scene().measures.max(1);
+		true
+	}
+
+	/// Each track loaded, by the path it was loaded from.
+	fn loaded(&self) -> Vec<(String, TrackId)> {
+		(self.scenes.iter())
+			.map(|scene| scene.path.to_string())
+			.zip(self.tracks.iter().copied())
+			.collect()
+	}
+
+	fn with_loaded(
+		scenes: Vec<Rc<Scene>>,
+		audio: &mut dyn AudioSource,
+		loaded: Vec<(String, TrackId)>,
+	) -> Self {
+		let tracks = load_tracks(&scenes, audio, loaded);
+		let duration = audio.duration(tracks[0]);
+
+		Playback {
+			transport: Transport::new(grid(&scenes[0], duration), duration, scenes[0].loop_),
+			scenes,
+			tracks,
+			state: State::Loading,
+			current_scene: 0,
+			next_scene: None,
+			current_measure: 0,
+			scheduled: None,
+			ticks: vec![],
+		}
+	}
+
+	pub fn state(&self) -> State {
+		self.state
+	}
+
+	pub fn scenes(&self) -> &[Rc<Scene>] {
+		&self.scenes
+	}
+
+	pub fn current(&self) -> usize {
+		self.current_scene
+	}
+
+	pub fn scene(&self) -> &Scene {
+		&self.scenes[self.current_scene]
+	}
+
+	pub fn track(&self) -> TrackId {
+		self.tracks[self.current_scene]
+	}
+
+	/// Why the track of one of the scenes failed to load, if one did.
+	pub fn error(&self, audio: &dyn AudioSource) -> Option<String> {
+		self.tracks.iter().find_map(|&track| audio.error(track))
+	}
+
+	pub fn position(&self) -> Position {
+		self.transport.position()
+	}
+
+	pub fn ticks(&self) -> &[Event] {
+		&self.ticks
+	}
+
+	/// The current track's beat grid, or one spreading the scene's measures evenly over the
+	/// track if it has none.
+	pub fn grid(&self, audio: &dyn AudioSource) -> BeatGrid {
+		grid(self.scene(), audio.duration(self.track()))
+	}
+
+	/// Starts playing once the first track is ready. Call it from user gestures.
+	pub fn start(&mut self, audio: &mut dyn AudioSource) {
+		audio.resume();
+		if self.state == State::Ready {
+			self.play(audio);
+			self.state = State::Playing;
+		}
+	}
+
+	/// Moves on to the next scene at the end of the current measure.
+	pub fn queue_next(&mut self) {
+		if self.state == State::Playing {
+			self.state = State::Queued;
+		}
+	}
+
+	/// Moves on to scene `index` at the end of the current measure instead, unless the next scene
+	/// has already been handed to the audio backend.
+	pub fn queue(&mut self, index: usize) {
+		let playing = matches!(self.state, State::Playing | State::Queued);
+		if playing && index < self.scenes.len() && self.scheduled.is_none() {
+			self.next_scene = Some(index);
+			self.state = State::Queued;
+		}
+	}
+
+	/// The scene to move on to, if there is one.
+	fn next(&self) -> Option<usize> {
+		let next = self.next_scene.unwrap_or(self.current_scene + 1);
+		(next < self.scenes.len()).then_some(next)
+	}
+
+	pub fn update(&mut self, audio: &mut dyn AudioSource) {
+		self.ticks.clear();
+		let ready = audio.ready(self.track());
+		match self.state {
+			State::Loading if ready => self.state = State::Ready,
+			State::Transitioning if ready => {
+				self.play(audio);
+				self.state = State::Playing;
+			}
+			State::Playing | State::Queued => {
+				self.schedule(audio);
+				self.follow(audio);
+			}
+			_ => {}
+		}
+	}
+
+	/// Counts the measures passed since the last frame, and changes scenes on the first bar line
+	/// after one is queued.
+	fn follow(&mut self, audio: &mut dyn AudioSource) {
+		let scene = self.scenes[self.current_scene].clone();
+		let track = self.track();
+		let clock = audio.clock();
+		let grid = self.grid(audio);
+		self.transport
+			.set_track(grid, audio.duration(track), scene.loop_);
+		if !audio.paused(track) {
+			self.transport.follow(clock, audio.current_time(track));
+		}
+
+		for event in self.transport.advance(clock) {
+			self.ticks.push(event);
+			if event.boundary != Boundary::Bar {
+				continue;
+			}
+			self.current_measure += 1;
+
+			if self.current_measure == scene.measures {
+				self.current_measure = 0;
+				if !scene.loop_ {
+					self.state = State::Queued;
+				}
+			}
+
+			if self.state == State::Queued {
+				self.advance(audio);
+				// The rest of this frame's events were on the previous track.
+				self.ticks.clear();
+				break;
+			}
+		}
+	}
+
+	/// Hands the coming scene change to the audio backend once the bar line it falls on is close,
+	/// so that it happens on the bar line exactly instead of on the first frame after.
+	fn schedule(&mut self, audio: &mut dyn AudioSource) {
+		let scene = self.scene();
+		let ending = self.state == State::Queued
+			|| (!scene.loop_ && self.current_measure + 1 == scene.measures);
+		if self.scheduled.is_some() || !ending {
+			return;
+		}
+		let Some(next_index) = self.next() else {
+			return;
+		};
+		let (track, next) = (self.track(), self.tracks[next_index]);
+		let Some(bar) = self.transport.next_bar() else {
+			return;
+		};
+		if bar.time - audio.clock() > LOOKAHEAD || !audio.ready(next) || next == track {
+			return;
+		}
+
+		let next_scene = &self.scenes[next_index];
+		match next_scene.transition {
+			Transition::Cut => {
+				audio.pause_at(track, bar.time);
+				audio.fade(next, Fade::FULL);
+			}
+			Transition::Crossfade(beats) => {
+				let grid = self.grid(audio);
+				let beat = (bar.bar * grid.beats_per_bar as i64) as f64;
+				let length = grid.time_at(beat + beats) - grid.time_at(beat);
+				let fade = |from, to| Fade {
+					start: bar.time,
+					length,
+					from,
+					to,
+				};
+				audio.fade(track, fade(1.0, 0.0));
+				audio.pause_at(track, bar.time + length);
+				audio.fade(next, fade(0.0, 1.0));
+			}
+		}
+		audio.seek(next, 0.0);
+		audio.play_at(next, next_scene.loop_, bar.time);
+		self.scheduled = Some(bar.time);
+	}
+
+	/// Stops the current scene and moves on to the next one, if there is one.
+	fn advance(&mut self, audio: &mut dyn AudioSource) {
+		let previous = self.track();
+		let Some(next) = self.next() else {
+			audio.pause(previous);
+			self.transport.pause(audio.clock());
+			self.state = State::Finished;
+			return;
+		};
+
+		self.current_scene = next;
+		self.next_scene = None;
+		self.current_measure = 0;
+		self.state = State::Playing;
+		let track = self.track();
+		// The track, and the transport following it, carry on into the next scene.
+		if track == previous {
+			return;
+		}
+
+		let duration = audio.duration(track);
+		self.transport = Transport::new(self.grid(audio), duration, self.scene().loop_);
+		if let Some(start) = self.scheduled.take() {
+			self.transport.play(start, 0.0);
+			return;
+		}
+
+		// Too late to schedule, so cut over as soon as the next track is ready.
+		audio.pause(previous);
+		audio.fade(track, Fade::FULL);
+		audio.seek(track, 0.0);
+		self.state = State::Transitioning;
+		self.update(audio);
+	}
+
+	fn play(&mut self, audio: &mut dyn AudioSource) {
+		let track = self.track();
+		audio.play(track, self.scene().loop_);
+		self.transport
+			.play(audio.clock(), audio.current_time(track));
+	}
+}
+
+/// The track of each scene, loading the ones that aren't `loaded` yet.
+fn load_tracks(
+	scenes: &[Rc<Scene>],
+	audio: &mut dyn AudioSource,
+	mut loaded: Vec<(String, TrackId)>,
+) -> Vec<TrackId> {
+	(scenes.iter())
+		.map(
+			|scene| match loaded.iter().find(|(path, _)| *path == scene.path) {
+				Some(&(_, track)) => track,
+				None => {
+					let track = audio.load(&scene.path);
+					loaded.push((scene.path.to_string(), track));
+					track
+				}
+			},
+		)
+		.collect()
+}
+
+/// The beat grid of the track `scene` plays, or one spreading its measures evenly over the
+/// track's `duration` if it has none.
+fn grid(scene: &Scene, duration: f64) -> BeatGrid {
+	tracks::grid(&scene.path)
+		.copied()
+		.unwrap_or_else(|| BeatGrid::spanning(duration, scene.measures))
+}
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{
+			audio::{Call, MockAudio},
+			scenes::for_against,
+		},
+		std::borrow::Cow,
+	};
+
+	/// Each test track is 8 seconds of four bars, two seconds each.
+	const DURATION: f64 = 8.0;
+
+	fn scene(path: &'static str, loop_: bool, transition: Transition) -> Rc<Scene> {
+		Rc::new(Scene {
+			path: Cow::Borrowed(path),
+			loop_,
+			measures: 4,
+			transition,
+			..for_against::SCENES[0].clone()
+		})
+	}
+
+	/// Playback of `scenes`, started, with the audio it plays through.
+	fn started(scenes: Vec<Rc<Scene>>) -> (Playback, MockAudio) {
+		let mut audio = ["/a.mp3", "/b.mp3", "/c.mp3"]
+			.into_iter()
+			.fold(MockAudio::new(), |audio, path| {
+				audio.with_track(path, DURATION)
+			});
+		let mut playback = Playback::new(scenes, &mut audio);
+		playback.update(&mut audio);
+		playback.start(&mut audio);
+		(playback, audio)
+	}
+
+	/// Plays `seconds` of frames at 60 fps.
+	fn run(playback: &mut Playback, audio: &mut MockAudio, seconds: f64) {
+		for _ in 0..(seconds * 60.0).round() as usize {
+			audio.advance(1.0 / 60.0);
+			playback.update(audio);
+		}
+	}
+
+	/// When on the clock `track` was asked to start, if it was.
+	fn started_at(audio: &MockAudio, track: TrackId) -> Option<f64> {
+		audio.calls().into_iter().find_map(|call| match call {
+			Call::Play(played, _, when) if played == track => Some(when),
+			_ => None,
+		})
+	}
+
+	fn close(a: f64, b: f64) -> bool {
+		(a - b).abs() < 1e-6
+	}
+
+	#[test]
+	fn waits_for_the_first_track_then_a_gesture() {
+		let mut audio = MockAudio::new().with_track("/a.mp3", DURATION);
+		let mut playback = Playback::new(vec![scene("/a.mp3", true, Transition::Cut)], &mut audio);
+		audio.set_ready(0, false);
+		playback.update(&mut audio);
+		assert_eq!(playback.state(), State::Loading);
+
+		audio.set_ready(0, true);
+		playback.update(&mut audio);
+		assert_eq!(playback.state(), State::Ready);
+		assert_eq!(started_at(&audio, 0), None);
+
+		playback.start(&mut audio);
+		assert_eq!(playback.state(), State::Playing);
+		assert_eq!(started_at(&audio, 0), Some(0.0));
+	}
+
+	#[test]
+	fn cuts_on_the_bar_line() {
+		let (mut playback, mut audio) = started(vec![
+			scene("/a.mp3", true, Transition::Cut),
+			scene("/b.mp3", true, Transition::Cut),
+		]);
+		run(&mut playback, &mut audio, 0.5);
+		playback.queue_next();
+		assert_eq!(playback.state(), State::Queued);
+
+		// Nothing is handed over until the bar line is within the lookahead.
+		run(&mut playback, &mut audio, 1.5 - LOOKAHEAD - 0.05);
+		assert_eq!(started_at(&audio, 1), None);
+		run(&mut playback, &mut audio, 0.1);
+		let start = started_at(&audio, 1).unwrap();
+		assert!(close(start, 2.0), "{start}");
+		assert!(audio
+			.calls()
+			.iter()
+			.any(|call| matches!(call, &Call::Pause(0, when) if close(when, 2.0))));
+		assert_eq!(playback.current(), 0);
+
+		run(&mut playback, &mut audio, 0.2);
+		assert_eq!(playback.state(), State::Playing);
+		assert_eq!(playback.current(), 1);
+		assert_eq!(playback.track(), 1);
+	}
+
+	#[test]
+	fn crossfades_from_the_bar_line() {
+		let (mut playback, mut audio) = started(vec![
+			scene("/a.mp3", true, Transition::Cut),
+			scene("/b.mp3", true, Transition::Crossfade(2.0)),
+		]);
+		playback.queue_next();
+		run(&mut playback, &mut audio, 1.9);
+		let start = started_at(&audio, 1).unwrap();
+		assert!(close(start, 2.0), "{start}");
+		// Two beats at 120 BPM.
+		let pause = (audio.calls().into_iter())
+			.find_map(|call| match call {
+				Call::Pause(0, when) => Some(when),
+				_ => None,
+			})
+			.unwrap();
+		assert!(close(pause, 3.0), "{pause}");
+
+		run(&mut playback, &mut audio, 0.6);
+		let half = std::f32::consts::FRAC_1_SQRT_2;
+		assert!((audio.gain(0) - half).abs() < 0.05, "{}", audio.gain(0));
+		assert!((audio.gain(1) - half).abs() < 0.05, "{}", audio.gain(1));
+		assert_eq!(playback.current(), 1);
+
+		run(&mut playback, &mut audio, 1.0);
+		assert!(audio.gain(0).abs() < 1e-6);
+		assert!((audio.gain(1) - 1.0).abs() < 1e-6);
+		assert!(audio.paused(0));
+	}
+
+	#[test]
+	fn waits_on_the_bar_line_for_a_late_track() {
+		let (mut playback, mut audio) = started(vec![
+			scene("/a.mp3", true, Transition::Cut),
+			scene("/b.mp3", true, Transition::Cut),
+		]);
+		audio.set_ready(1, false);
+		playback.queue_next();
+		run(&mut playback, &mut audio, 2.5);
+		assert_eq!(playback.state(), State::Transitioning);
+		assert_eq!(playback.current(), 1);
+		assert_eq!(started_at(&audio, 1), None);
+		assert!(audio.paused(0));
+
+		audio.set_ready(1, true);
+		run(&mut playback, &mut audio, 0.1);
+		assert_eq!(playback.state(), State::Playing);
+		assert!(started_at(&audio, 1).is_some());
+	}
+
+	#[test]
+	fn scenes_sharing_a_track_carry_on_with_it() {
+		let (mut playback, mut audio) = started(vec![
+			scene("/a.mp3", true, Transition::Cut),
+			scene("/a.mp3", true, Transition::Cut),
+		]);
+		playback.queue_next();
+		run(&mut playback, &mut audio, 2.5);
+		assert_eq!(playback.state(), State::Playing);
+		assert_eq!(playback.current(), 1);
+		assert_eq!(playback.track(), 0);
+		let calls = audio.calls();
+		assert_eq!(
+			calls
+				.iter()
+				.filter(|call| matches!(call, Call::Load(_)))
+				.count(),
+			1
+		);
+		assert!(!calls.iter().any(|call| matches!(call, Call::Pause(..))));
+		assert!(!audio.paused(0));
+		assert!(close(audio.current_time(0), 2.5));
+	}
+
+	#[test]
+	fn queue_jumps_to_any_scene() {
+		let (mut playback, mut audio) = started(vec![
+			scene("/a.mp3", true, Transition::Cut),
+			scene("/b.mp3", true, Transition::Cut),
+			scene("/c.mp3", true, Transition::Cut),
+		]);
+		playback.queue(5);
+		assert_eq!(playback.state(), State::Playing);
+
+		playback.queue(2);
+		run(&mut playback, &mut audio, 2.5);
+		assert_eq!(playback.current(), 2);
+		assert_eq!(started_at(&audio, 1), None);
+
+		playback.queue(0);
+		run(&mut playback, &mut audio, 2.0);
+		assert_eq!(playback.current(), 0);
+	}
+
+	#[test]
+	fn reports_tracks_that_fail_to_load() {
+		let (playback, audio) = started(vec![
+			scene("/a.mp3", true, Transition::Cut),
+			scene("/b.mp3", true, Transition::Cut),
+		]);
+		assert_eq!(playback.error(&audio), None);
+		audio.fail(1, "/b.mp3: not found");
+		assert_eq!(playback.error(&audio).as_deref(), Some("/b.mp3: not found"));
+		assert!(!audio.ready(1));
+	}
+
+	#[test]
+	fn reloads_follow_the_playing_track_through_the_list() {
+		let (mut playback, mut audio) = started(vec![
+			scene("/a.mp3", true, Transition::Cut),
+			scene("/b.mp3", true, Transition::Cut),
+		]);
+		run(&mut playback, &mut audio, 2.5);
+		let before = audio.calls().len();
+
+		let scenes = vec![
+			scene("/c.mp3", true, Transition::Cut),
+			scene("/b.mp3", true, Transition::Cut),
+			scene("/a.mp3", true, Transition::Cut),
+		];
+		assert!(playback.reload_scenes(scenes, &mut audio));
+		assert_eq!((playback.state(), playback.current()), (State::Playing, 2));
+		assert_eq!(audio.path(playback.track()), "/a.mp3");
+		// Only the new track loads, and the playing one carries on where it was.
+		assert_eq!(audio.calls()[before..], [Call::Load("/c.mp3".to_owned())]);
+		assert!(!audio.paused(0));
+		run(&mut playback, &mut audio, 2.0);
+		assert_eq!(playback.position().bar, 2);
+		assert!(close(audio.current_time(0), 4.5));
+	}
+
+	#[test]
+	fn reloads_pass_a_change_of_looping_on() {
+		let (mut playback, mut audio) = started(vec![scene("/a.mp3", true, Transition::Cut)]);
+		run(&mut playback, &mut audio, 2.5);
+		let scenes = vec![scene("/a.mp3", false, Transition::Cut)];
+		assert!(playback.reload_scenes(scenes, &mut audio));
+		let play = audio.calls().last().cloned();
+		assert!(matches!(play, Some(Call::Play(0, false, when)) if close(when, 2.5)));
+		assert!(close(audio.current_time(0), 2.5));
+		// The track stops at its end instead of going round again.
+		run(&mut playback, &mut audio, 6.0);
+		assert_eq!(playback.state(), State::Finished);
+		assert!(audio.paused(0));
+	}
+
+	#[test]
+	fn reloads_survive_scenes_without_measures() {
+		let (mut playback, mut audio) = started(vec![scene("/a.mp3", true, Transition::Cut)]);
+		run(&mut playback, &mut audio, 2.5);
+		let scenes = vec![Rc::new(Scene {
+			measures: 0,
+			..(*scene("/a.mp3", true, Transition::Cut)).clone()
+		})];
+		assert!(playback.reload_scenes(scenes, &mut audio));
+		assert_eq!(playback.state(), State::Playing);
+	}
+
+	#[test]
+	fn reloads_without_the_playing_track_start_over() {
+		let (mut playback, mut audio) = started(vec![
+			scene("/a.mp3", true, Transition::Cut),
+			scene("/b.mp3", true, Transition::Cut),
+		]);
+		playback.queue_next();
+		run(&mut playback, &mut audio, 1.9);
+		// The change to /b.mp3 is handed to the audio backend by now.
+		let scenes = vec![scene("/c.mp3", true, Transition::Cut)];
+		assert!(!playback.reload_scenes(scenes.clone(), &mut audio));
+
+		run(&mut playback, &mut audio, 0.5);
+		assert!(playback.reload_scenes(scenes, &mut audio));
+		assert_eq!(playback.state(), State::Loading);
+		assert!(audio.paused(1));
+	}
+
+	#[test]
+	fn finishes_after_the_last_scene() {
+		let (mut playback, mut audio) = started(vec![
+			scene("/a.mp3", true, Transition::Cut),
+			scene("/b.mp3", false, Transition::Cut),
+		]);
+		playback.queue_next();
+		run(&mut playback, &mut audio, 2.5);
+		assert_eq!(playback.current(), 1);
+
+		// The last scene doesn't loop, so it moves on by itself after its four measures.
+		run(&mut playback, &mut audio, DURATION - 1.0);
+		assert_eq!(playback.state(), State::Playing);
+		run(&mut playback, &mut audio, 1.0);
+		assert_eq!(playback.state(), State::Finished);
+		assert!(audio.paused(1));
+	}
+}
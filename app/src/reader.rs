@@ -0,0 +1,39 @@
+//! Reading binary formats a field at a time, for analysis tracks and MIDI files.
+
+/// What's left to read.
+pub struct Reader<'a>(pub &'a [u8]);
+
+impl<'a> Reader<'a> {
+	pub fn take(&mut self, count: usize) -> Result<&'a [u8], &'static str> {
+		if self.0.len() < count {
+			return Err("truncated");
+		}
+		let (taken, rest) = self.0.split_at(count);
+		self.0 = rest;
+		Ok(taken)
+	}
+
+	pub fn array<const N: usize>(&mut self) -> Result<[u8; N], &'static str> {
+		Ok(self.take(N)?.try_into().unwrap())
+	}
+
+	/// A chunk's data, after its length as a big endian `u32`.
+	pub fn chunk(&mut self) -> Result<&'a [u8], &'static str> {
+		let length = u32::from_be_bytes(self.array()?) as usize;
+		self.take(length)
+	}
+
+	/// A variable length quantity: seven bits a byte, most significant first, with the top bit
+	/// set on every byte but the last.
+	pub fn variable(&mut self) -> Result<u64, &'static str> {
+		let mut value = 0;
+		for _ in 0..4 {
+			let byte = self.take(1)?[0];
+			value = (value << 7) | (byte & 0x7f) as u64;
+			if byte < 0x80 {
+				return Ok(value);
+			}
+		}
+		Err("variable length quantity too long")
+	}
+}
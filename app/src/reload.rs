@@ -0,0 +1,111 @@
+//! Files under `static/` changing while the app runs, so scenes can be tweaked without starting
+//! over. The browser hears about them from the server, when it runs with `--dev`; the native
+//! build checks the directory itself.
+
+#[cfg(not(target_arch = "wasm32"))]
+use {
+	crate::assets::STATIC_DIR,
+	std::{
+		fs,
+		path::Path,
+		time::{Duration, Instant, SystemTime},
+	},
+};
+#[cfg(target_arch = "wasm32")]
+use {
+	std::{cell::RefCell, rc::Rc},
+	wasm_bindgen::{closure::Closure, JsCast},
+	web_sys::{EventSource, MessageEvent},
+};
+
+/// Where the server announces changes in development mode.
+#[cfg(target_arch = "wasm32")]
+const EVENTS_PATH: &str = "/events";
+
+/// How often the native build looks for changes.
+#[cfg(not(target_arch = "wasm32"))]
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[cfg(target_arch = "wasm32")]
+pub struct Changes {
+	changed: Rc<RefCell<Vec<String>>>,
+	/// Kept open for as long as the changes are wanted.
+	_source: Option<EventSource>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Changes {
+	/// Starts listening. A server that isn't in development mode has nothing at `/events`, and
+	/// then nothing ever changes.
+	pub fn watch() -> Self {
+		let changed = Rc::new(RefCell::new(vec![]));
+		let source = EventSource::new(EVENTS_PATH).ok();
+		if let Some(source) = &source {
+			let queue = changed.clone();
+			let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+				if let Some(path) = event.data().as_string() {
+					queue.borrow_mut().push(path);
+				}
+			});
+			source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+			on_message.forget();
+		}
+		Changes {
+			changed,
+			_source: source,
+		}
+	}
+
+	/// Paths, as the server hosts them, that changed since the last call.
+	pub fn take(&mut self) -> Vec<String> {
+		self.changed.take()
+	}
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Changes {
+	/// When the files were last looked at.
+	checked: SystemTime,
+	polled: Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Changes {
+	pub fn watch() -> Self {
+		Changes {
+			checked: SystemTime::now(),
+			polled: Instant::now(),
+		}
+	}
+
+	/// Paths, as the server hosts them, that changed since the last call.
+	pub fn take(&mut self) -> Vec<String> {
+		if self.polled.elapsed() < POLL_INTERVAL {
+			return vec![];
+		}
+		self.polled = Instant::now();
+		let since = std::mem::replace(&mut self.checked, SystemTime::now());
+		let mut changed = vec![];
+		modified(Path::new(STATIC_DIR), "", since, &mut changed);
+		changed
+	}
+}
+
+/// Adds the files under `dir`, hosted under `hosted`, modified at or after `since`.
+#[cfg(not(target_arch = "wasm32"))]
+fn modified(dir: &Path, hosted: &str, since: SystemTime, changed: &mut Vec<String>) {
+	let Ok(entries) = fs::read_dir(dir) else {
+		return;
+	};
+	for entry in entries.flatten() {
+		let path = format!("{hosted}/{}", entry.file_name().to_string_lossy());
+		let Ok(metadata) = entry.metadata() else {
+			continue;
+		};
+		if metadata.is_dir() {
+			modified(&entry.path(), &path, since, changed);
+		} else if metadata.modified().is_ok_and(|time| time >= since) {
+			changed.push(path);
+		}
+	}
+}
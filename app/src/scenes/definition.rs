@@ -0,0 +1,590 @@
+//! Scenes described in `static/scenes.ron` instead of compiled in, so they can change without a
+//! rebuild. The file lists the show in order; each entry is either a compiled scene, by its
+//! index in `Registry::compiled`, or a scene of its own that draws a line from every point of the
+//! grid the way its render recipe says:
+//!
+//! ```ron
+//! #![enable(implicit_some, unwrap_variant_newtypes)]
+//! [
+//! 	Compiled(0),
+//! 	Scene(
+//! 		path: "/bu/2.mp3",
+//! 		measures: 8,
+//! 		render: Lines(
+//! 			gray: [(0.5, [Half]), (0.4, [Signal("mid")])],
+//! 			end: (x: [(amplitude: 25.0, shape: Cos, phase: [(1.0, [Time, Noise])])]),
+//! 		),
+//! 	),
+//! 	Scene(
+//! 		path: "/bu/2.mp3",
+//! 		measures: 8,
+//! 		render: Script("x1 = x + 25 * cos(time * noise); gray = 0.5 * half + 0.4 * mid"),
+//! 	),
+//! ]
+//! ```
+
+use {
+	super::{
+		expression::{Environment, Named, Program},
+		scale_coordinates, Area, Modifiers, Render, RenderFn, Scene, Transition,
+	},
+	crate::{
+		analysis::{Band, Bands, Envelope, Follower, Unit},
+		model::{Model, CANVAS_HEIGHT, CANVAS_WIDTH},
+	},
+	nannou::{
+		noise::{MultiFractal, NoiseFn, RidgedMulti},
+		prelude::*,
+	},
+	serde::Deserialize,
+	std::{borrow::Cow, rc::Rc},
+};
+
+/// Where the server hosts the scene list.
+pub const SCENES_PATH: &str = "/scenes.ron";
+
+#[derive(Deserialize)]
+enum Entry {
+	Compiled(usize),
+	Scene(Definition),
+}
+
+/// Everything a `Scene` has, with the same defaults the compiled scenes mostly use.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Definition {
+	#[serde(default)]
+	title: String,
+	path: String,
+	#[serde(rename = "loop", default = "looping")]
+	loop_: bool,
+	measures: usize,
+	#[serde(default)]
+	transition: Transition,
+	#[serde(default)]
+	bands: Option<BandsDefinition>,
+	#[serde(default)]
+	waveform: usize,
+	#[serde(default)]
+	pattern: Option<String>,
+	#[serde(default)]
+	envelopes: Vec<(String, Envelope)>,
+	/// Hotspots the recipe may light up.
+	#[serde(default)]
+	boxes: Vec<Area>,
+	#[serde(default)]
+	noise: Noise,
+	render: Recipe,
+}
+
+fn looping() -> bool {
+	true
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BandsDefinition {
+	fft_size: usize,
+	bands: Vec<(String, f32, f32)>,
+}
+
+/// The ridged noise `Input::Noise2` samples, at the point being drawn and the depth this sums
+/// to. Scripts give the depth themselves, calling `noise2(depth)`.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Noise {
+	octaves: usize,
+	frequency: f64,
+	depth: Sum,
+}
+
+impl Default for Noise {
+	fn default() -> Self {
+		Noise {
+			// `RidgedMulti`'s own, which the model's noise uses.
+			octaves: 6,
+			frequency: 1.0,
+			depth: Sum::default(),
+		}
+	}
+}
+
+/// How a scene draws each point of the grid.
+#[derive(Deserialize)]
+enum Recipe {
+	/// A line from the point moved by `start` to the point moved by `end`, as gray as `gray`
+	/// says. With `hotspots`, lines starting inside one of the scene's boxes are white.
+	Lines {
+		gray: Sum,
+		#[serde(default)]
+		start: Offset,
+		#[serde(default)]
+		end: Offset,
+		#[serde(default)]
+		hotspots: bool,
+	},
+	/// A script, see `expression`. It's given `SCRIPT_INPUTS` and draws a line from `x0`, `y0` to
+	/// `x1`, `y1`, as gray as `gray`, if they aren't the same point, and a circle at `x0`, `y0` if
+	/// `radius` is more than 0.
+	Script(String),
+}
+
+/// The variables a script starts with at each point. The first six are what it draws, set to
+/// a point at `x`, `y` with no radius and full gray unless the script changes them.
+const SCRIPT_INPUTS: &[&str] = &[
+	"x0",
+	"y0",
+	"x1",
+	"y1",
+	"radius",
+	"gray",
+	"i",
+	"j",
+	"x",
+	"y",
+	"column",
+	"row",
+	"time",
+	"noise",
+	"whole",
+	"half",
+	"quarter",
+	"eighth",
+	"sixteenth",
+	"bass",
+	"mid",
+	"treble",
+	"rms",
+	"centroid",
+	"flux",
+	"onsets",
+	"balance",
+	"width",
+	"wave",
+	"mouse_x",
+	"mouse_y",
+];
+
+/// Waves added up along each axis.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Offset {
+	x: Vec<Wave>,
+	y: Vec<Wave>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Wave {
+	amplitude: f32,
+	shape: Shape,
+	phase: Sum,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+enum Shape {
+	Sin,
+	Cos,
+	/// The phase itself, for offsets that don't swing.
+	Linear,
+}
+
+/// A sum of terms, each a weight times every input it lists. A term with no inputs is just its
+/// weight.
+#[derive(Default, Deserialize)]
+#[serde(transparent)]
+struct Sum(Vec<(f32, Vec<Input>)>);
+
+#[derive(Deserialize)]
+enum Input {
+	/// The time `render` is given.
+	Time,
+	/// The model's noise map under the point, from about -1 to 1.
+	Noise,
+	/// The scene's own noise, see `Noise`.
+	Noise2,
+	/// The point's column and row, from 0 to 1.
+	Column,
+	Row,
+	Whole,
+	Half,
+	Quarter,
+	Eighth,
+	Sixteenth,
+	/// Any of `Modifiers::signal`.
+	Signal(String),
+	/// The value of a follower the scene asked for, 0 if it didn't.
+	Envelope(String),
+	/// Velocity of the pattern's lane at the step playing.
+	Lane(String),
+}
+
+/// What inputs read from, for one point.
+struct Point<'a> {
+	modifiers: &'a Modifiers,
+	column: f32,
+	row: f32,
+	time: f32,
+	noise: f32,
+	noise2: f32,
+}
+
+impl Sum {
+	fn value(&self, point: &Point) -> f32 {
+		(self.0.iter())
+			.map(|(weight, inputs)| {
+				weight
+					* inputs
+						.iter()
+						.map(|input| input.value(point))
+						.product::<f32>()
+			})
+			.sum()
+	}
+}
+
+impl Input {
+	fn value(&self, point: &Point) -> f32 {
+		let modifiers = point.modifiers;
+		match self {
+			Input::Time => point.time,
+			Input::Noise => point.noise,
+			Input::Noise2 => point.noise2,
+			Input::Column => point.column,
+			Input::Row => point.row,
+			Input::Whole => modifiers.whole,
+			Input::Half => modifiers.half,
+			Input::Quarter => modifiers.quarter,
+			Input::Eighth => modifiers.eighth,
+			Input::Sixteenth => modifiers.sixteenth,
+			Input::Signal(name) => modifiers.signal(name),
+			Input::Envelope(name) => modifiers.envelope(name).map_or(0.0, Follower::value),
+			Input::Lane(name) => modifiers.lane(name),
+		}
+	}
+}
+
+/// What scripts read by name, for one point.
+struct Cell<'a> {
+	modifiers: &'a Modifiers,
+	noise: &'a RidgedMulti,
+	i: usize,
+	j: usize,
+}
+
+impl Environment for Cell<'_> {
+	fn named(&self, function: Named, name: &str) -> f32 {
+		let modifiers = self.modifiers;
+		match function {
+			Named::Band => modifiers.band(name),
+			Named::Signal => modifiers.signal(name),
+			Named::Envelope => modifiers.envelope(name).map_or(0.0, Follower::value),
+			Named::Lane => modifiers.lane(name),
+			Named::Hit => modifiers.hit(name).unwrap_or(0.0),
+		}
+	}
+
+	fn noise(&self, depth: f32) -> f32 {
+		self.noise.get([self.i as f64, self.j as f64, depth as f64]) as f32
+	}
+}
+
+impl Offset {
+	fn value(&self, point: &Point) -> Vec2 {
+		let sum = |waves: &[Wave]| waves.iter().map(|wave| wave.value(point)).sum();
+		Vec2::new(sum(&self.x), sum(&self.y))
+	}
+}
+
+impl Wave {
+	fn value(&self, point: &Point) -> f32 {
+		let phase = self.phase.value(point);
+		self.amplitude
+			* match self.shape {
+				Shape::Sin => phase.sin(),
+				Shape::Cos => phase.cos(),
+				Shape::Linear => phase,
+			}
+	}
+}
+
+/// Parses a scene list. `compiled` are the scenes `Compiled` entries refer to.
+pub fn parse(bytes: &[u8], compiled: &[Rc<Scene>]) -> Result<Vec<Rc<Scene>>, String> {
+	let entries: Vec<Entry> = ron::de::from_bytes(bytes).map_err(|error| error.to_string())?;
+	if entries.is_empty() {
+		return Err("no scenes".to_owned());
+	}
+	(entries.into_iter().enumerate())
+		.map(|(index, entry)| {
+			let scene = match entry {
+				Entry::Compiled(compiled_index) => compiled
+					.get(compiled_index)
+					.cloned()
+					.ok_or_else(|| format!("there is no compiled scene {compiled_index}")),
+				Entry::Scene(definition) => definition.build(),
+			};
+			scene.map_err(|error| format!("scene {index}: {error}"))
+		})
+		.collect()
+}
+
+impl Definition {
+	fn build(self) -> Result<Rc<Scene>, String> {
+		if self.measures == 0 {
+			return Err("a scene needs at least one measure".to_owned());
+		}
+		if !hosted(&self.path) {
+			return Err(format!("there is no track {}", self.path));
+		}
+		let bands = match self.bands {
+			Some(bands) => {
+				let fft_size = bands.fft_size;
+				if !(32..=32768).contains(&fft_size) || !fft_size.is_power_of_two() {
+					return Err(format!(
+						"FFT size {fft_size} isn't a power of two from 32 to 32768"
+					));
+				}
+				let bands = (bands.bands.into_iter())
+					.map(|(name, low, high)| Band {
+						name: Cow::Owned(name),
+						low,
+						high,
+					})
+					.collect::<Vec<_>>();
+				Some(Cow::Owned(Bands {
+					fft_size,
+					unit: Unit::Hertz,
+					bands: Cow::Owned(bands),
+				}))
+			}
+			None => None,
+		};
+		let envelopes = (self.envelopes.into_iter())
+			.map(|(name, envelope)| (Cow::Owned(name), envelope))
+			.collect::<Vec<_>>();
+		let boxes = self.boxes;
+		let noise = RidgedMulti::new()
+			.set_octaves(self.noise.octaves)
+			.set_frequency(self.noise.frequency);
+		let depth = self.noise.depth;
+
+		let render: Rc<RenderFn> = match self.render {
+			Recipe::Lines {
+				gray,
+				start,
+				end,
+				hotspots,
+			} => {
+				let boxes = boxes.clone();
+				Rc::new(
+					move |draw: &Draw,
+					      model: &Model,
+					      i: usize,
+					      j: usize,
+					      time: f32,
+					      modifiers: &Modifiers| {
+						let mut point = Point {
+							modifiers,
+							column: i as f32 / (CANVAS_WIDTH - 1) as f32,
+							row: j as f32 / (CANVAS_HEIGHT - 1) as f32,
+							time,
+							noise: model.noisemap.get_value(i, j) as f32,
+							noise2: 0.0,
+						};
+						let z = depth.value(&point) as f64;
+						point.noise2 = noise.get([i as f64, j as f64, z]) as f32;
+
+						let (x, y) = scale_coordinates(model, i, j);
+						let from = Vec2::new(x, y) + start.value(&point);
+						let to = Vec2::new(x, y) + end.value(&point);
+						let lit = hotspots && boxes.iter().any(|area| area.inside(from.x, from.y));
+						let gray = if lit { 1.0 } else { gray.value(&point) };
+
+						draw.line().gray(gray).points(from, to);
+					},
+				)
+			}
+			Recipe::Script(source) => {
+				let program = Program::compile(&source, SCRIPT_INPUTS)
+					.map_err(|error| format!("render script, {error}"))?;
+				Rc::new(
+					move |draw: &Draw,
+					      model: &Model,
+					      i: usize,
+					      j: usize,
+					      time: f32,
+					      modifiers: &Modifiers| {
+						let cell = Cell {
+							modifiers,
+							noise: &noise,
+							i,
+							j,
+						};
+						draw_script(&program, &cell, draw, model, time)
+					},
+				)
+			}
+		};
+
+		Ok(Rc::new(Scene {
+			title: Cow::Owned(self.title),
+			loop_: self.loop_,
+			path: Cow::Owned(self.path),
+			measures: self.measures,
+			transition: self.transition,
+			bands,
+			waveform: self.waveform,
+			// Scripts don't read the harmony.
+			chroma: false,
+			pattern: self.pattern.map(Cow::Owned),
+			envelopes: Cow::Owned(envelopes),
+			render: Render::Defined(render),
+			boxes: Cow::Owned(boxes),
+		}))
+	}
+}
+
+/// Runs `program` for `cell` and draws what it comes out with.
+/// Whether the server hosts a track at `path`, as far as can be told without asking it. The
+/// browser only finds out once the track fails to load.
+fn hosted(path: &str) -> bool {
+	let track = path.starts_with('/') && path.ends_with(".mp3");
+	#[cfg(not(target_arch = "wasm32"))]
+	let track = track && {
+		let file = format!("{}{path}", crate::assets::STATIC_DIR);
+		std::path::Path::new(&file).is_file()
+	};
+	track
+}
+
+fn draw_script(program: &Program, cell: &Cell, draw: &Draw, model: &Model, time: f32) {
+	let (modifiers, i, j) = (cell.modifiers, cell.i, cell.j);
+	let (x, y) = scale_coordinates(model, i, j);
+	let mut variables = program.variables();
+	variables[..SCRIPT_INPUTS.len()].copy_from_slice(&[
+		x,
+		y,
+		x,
+		y,
+		0.0,
+		1.0,
+		i as f32,
+		j as f32,
+		x,
+		y,
+		i as f32 / (CANVAS_WIDTH - 1) as f32,
+		j as f32 / (CANVAS_HEIGHT - 1) as f32,
+		time,
+		model.noisemap.get_value(i, j) as f32,
+		modifiers.whole,
+		modifiers.half,
+		modifiers.quarter,
+		modifiers.eighth,
+		modifiers.sixteenth,
+		modifiers.bass,
+		modifiers.mid,
+		modifiers.treble,
+		modifiers.rms,
+		modifiers.centroid,
+		modifiers.flux,
+		modifiers.onsets,
+		modifiers.balance,
+		modifiers.width,
+		modifiers.waveform_column(i),
+		modifiers.mouse.x,
+		modifiers.mouse.y,
+	]);
+	program.run(&mut variables, cell);
+
+	let (from, to) = (
+		Vec2::new(variables[0], variables[1]),
+		Vec2::new(variables[2], variables[3]),
+	);
+	let (radius, gray) = (variables[4], variables[5]);
+	if from != to {
+		draw.line().gray(gray).points(from, to);
+	}
+	if radius > 0.0 {
+		draw.ellipse().xy(from).radius(radius).gray(gray);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use {super::*, crate::scenes::Registry};
+
+	/// A scene list of one scene, with `fields` added.
+	fn one_scene(fields: &str) -> String {
+		format!(
+			r#"#![enable(implicit_some)]
+			[Scene(path: "/bu/2.mp3", render: Script("gray = 1"), {fields})]"#
+		)
+	}
+
+	fn parse_error(list: &str) -> String {
+		let compiled = Registry::new(None).compiled();
+		parse(list.as_bytes(), &compiled).err().unwrap()
+	}
+
+	#[test]
+	fn the_shipped_scene_list_parses() {
+		let compiled = Registry::new(None).compiled();
+		let list = std::fs::read(format!("{}{SCENES_PATH}", crate::assets::STATIC_DIR)).unwrap();
+		let scenes = parse(&list, &compiled).unwrap();
+		assert!(!scenes.is_empty());
+		assert!(scenes.iter().all(|scene| scene.measures > 0));
+	}
+
+	#[test]
+	fn scenes_build_from_their_fields() {
+		let compiled = Registry::new(None).compiled();
+		let list = one_scene(r#"title: "Test", measures: 3, loop: false"#);
+		let scenes = parse(list.as_bytes(), &compiled).unwrap();
+		assert_eq!(scenes.len(), 1);
+		assert_eq!((&*scenes[0].title, &*scenes[0].path), ("Test", "/bu/2.mp3"));
+		assert_eq!((scenes[0].measures, scenes[0].loop_), (3, false));
+
+		let list = "[Compiled(0)]";
+		let scenes = parse(list.as_bytes(), &compiled).unwrap();
+		assert!(Rc::ptr_eq(&scenes[0], &compiled[0]));
+	}
+
+	#[test]
+	fn compiled_scenes_must_exist() {
+		let count = Registry::new(None).compiled().len();
+		assert_eq!(
+			parse_error(&format!("[Compiled(0), Compiled({count})]")),
+			format!("scene 1: there is no compiled scene {count}")
+		);
+	}
+
+	#[test]
+	fn scenes_need_a_measure() {
+		assert_eq!(
+			parse_error(&one_scene("measures: 0")),
+			"scene 0: a scene needs at least one measure"
+		);
+	}
+
+	#[test]
+	fn fft_sizes_are_powers_of_two_in_range() {
+		for fft_size in [100, 16, 65536] {
+			let bands = format!(r#"measures: 1, bands: (fft_size: {fft_size}, bands: [])"#);
+			assert_eq!(
+				parse_error(&one_scene(&bands)),
+				format!("scene 0: FFT size {fft_size} isn't a power of two from 32 to 32768")
+			);
+		}
+	}
+
+	#[test]
+	fn tracks_must_be_hosted() {
+		let list = r#"[Scene(path: "/nowhere.mp3", measures: 1, render: Script("gray = 1"))]"#;
+		assert_eq!(parse_error(list), "scene 0: there is no track /nowhere.mp3");
+	}
+
+	#[test]
+	fn lists_need_a_scene() {
+		assert_eq!(parse_error("[]"), "no scenes");
+	}
+}
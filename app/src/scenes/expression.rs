@@ -0,0 +1,664 @@
+//! A small language for render scripts, so the math that places each line can change without a
+//! rebuild. A script is a list of assignments, one per line or separated by `;`, run in order
+//! for every point of the grid:
+//!
+//! ```text
+//! # From here to the end of the line is a comment.
+//! swing = 25 * cos(noise * mid) + 20 * sin(time + noise2(mid / 10) * eighth / 10)
+//! x1 = x + swing
+//! gray = half + 0.2 * abs(quarter - 1) + 0.4 * band("sub")
+//! ```
+//!
+//! Numbers are `f32`, and may have an exponent like `1e-3`. There are `+ - * / % ^`, comparisons
+//! that come out 1 or 0, and the functions in `FUNCTIONS`. Variables and functions are looked up
+//! when a script is compiled, so a misspelling is an error then. Strings are only for naming
+//! bands, signals, envelopes and pattern lanes in the functions that read them; those names are
+//! only looked up as the script runs, and one that isn't there reads 0.
+
+use crate::notes::ParseError;
+
+type Function = fn(&[f32]) -> f32;
+
+/// Functions on numbers, by name, and how many arguments they take.
+const FUNCTIONS: &[(&str, usize, Function)] = &[
+	("sin", 1, |a| a[0].sin()),
+	("cos", 1, |a| a[0].cos()),
+	("tan", 1, |a| a[0].tan()),
+	("abs", 1, |a| a[0].abs()),
+	("sqrt", 1, |a| a[0].sqrt()),
+	("exp", 1, |a| a[0].exp()),
+	("ln", 1, |a| a[0].ln()),
+	("floor", 1, |a| a[0].floor()),
+	("ceil", 1, |a| a[0].ceil()),
+	("fract", 1, |a| a[0].fract()),
+	("sign", 1, |a| a[0].signum()),
+	("min", 2, |a| a[0].min(a[1])),
+	("max", 2, |a| a[0].max(a[1])),
+	("pow", 2, |a| a[0].powf(a[1])),
+	("atan2", 2, |a| a[0].atan2(a[1])),
+	("clamp", 3, |a| a[0].clamp(a[1], a[2].max(a[1]))),
+	("mix", 3, |a| a[0] + (a[1] - a[0]) * a[2]),
+	("if", 3, |a| if a[0] > 0.0 { a[1] } else { a[2] }),
+];
+
+/// Functions that read a value by name from outside the script, like `band("sub")`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Named {
+	Band,
+	Signal,
+	Envelope,
+	Lane,
+	Hit,
+}
+
+const NAMED: &[(&str, Named)] = &[
+	("band", Named::Band),
+	("signal", Named::Signal),
+	("envelope", Named::Envelope),
+	("lane", Named::Lane),
+	("hit", Named::Hit),
+];
+
+/// What a script can read besides its variables.
+pub trait Environment {
+	fn named(&self, function: Named, name: &str) -> f32;
+	/// Noise at the point being drawn, `depth` into it. Scripts call it `noise2`.
+	fn noise(&self, depth: f32) -> f32;
+}
+
+/// A compiled script.
+#[derive(Debug)]
+pub struct Program {
+	/// Names of the variables, the inputs first.
+	variables: Vec<String>,
+	statements: Vec<(usize, Expression)>,
+}
+
+#[derive(Debug)]
+enum Expression {
+	Number(f32),
+	Variable(usize),
+	Negate(Box<Expression>),
+	Binary(Operator, Box<Expression>, Box<Expression>),
+	Call(Function, Vec<Expression>),
+	Named(Named, String),
+	Noise(Box<Expression>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+	Add,
+	Subtract,
+	Multiply,
+	Divide,
+	Remainder,
+	Power,
+	Less,
+	LessOrEqual,
+	Greater,
+	GreaterOrEqual,
+}
+
+impl Operator {
+	/// How tightly it binds, and whether it groups to the right.
+	fn precedence(self) -> (u8, bool) {
+		match self {
+			Operator::Less
+			| Operator::LessOrEqual
+			| Operator::Greater
+			| Operator::GreaterOrEqual => (1, false),
+			Operator::Add | Operator::Subtract => (2, false),
+			Operator::Multiply | Operator::Divide | Operator::Remainder => (3, false),
+			Operator::Power => (5, true),
+		}
+	}
+
+	fn apply(self, a: f32, b: f32) -> f32 {
+		match self {
+			Operator::Add => a + b,
+			Operator::Subtract => a - b,
+			Operator::Multiply => a * b,
+			Operator::Divide => a / b,
+			Operator::Remainder => a % b,
+			Operator::Power => a.powf(b),
+			Operator::Less => (a < b) as u8 as f32,
+			Operator::LessOrEqual => (a <= b) as u8 as f32,
+			Operator::Greater => (a > b) as u8 as f32,
+			Operator::GreaterOrEqual => (a >= b) as u8 as f32,
+		}
+	}
+}
+
+/// Negation binds tighter than everything but `^`, so `-x^2` is `-(x^2)`.
+const NEGATE_PRECEDENCE: u8 = 4;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+	Number(f32),
+	Name(String),
+	Text(String),
+	Operator(Operator),
+	Assign,
+	Open,
+	Close,
+	Comma,
+	/// A line break or `;`.
+	End,
+}
+
+/// A token and the line and column it starts at, from 1.
+type Located = (Token, usize, usize);
+
+impl Program {
+	/// Compiles `source`. Scripts can read and assign `inputs`, which get the first variables in
+	/// that order, and assign anything else before reading it.
+	pub fn compile(source: &str, inputs: &[&str]) -> Result<Self, ParseError> {
+		let mut parser = Parser {
+			tokens: tokenize(source)?,
+			position: 0,
+			end: (
+				source.lines().count().max(1),
+				source.lines().last().map_or(0, |line| line.chars().count()) + 1,
+			),
+			variables: inputs.iter().map(|&input| input.to_owned()).collect(),
+		};
+		let mut statements = vec![];
+		loop {
+			while parser.eat(&Token::End) {}
+			if parser.peek().is_none() {
+				break;
+			}
+			let (name, line, column) = match parser.next() {
+				Some((Token::Name(name), line, column)) => (name, line, column),
+				other => return Err(parser.unexpected(other, "a variable to assign")),
+			};
+			if !parser.eat(&Token::Assign) {
+				let next = parser.next();
+				return Err(parser.unexpected(next, "`=`"));
+			}
+			if FUNCTIONS.iter().any(|&(function, ..)| function == name)
+				|| NAMED.iter().any(|&(function, _)| function == name)
+				|| name == "noise2"
+			{
+				return Err(error(line, column, format!("`{name}` is a function")));
+			}
+			let expression = parser.expression(0)?;
+			match parser.next() {
+				None | Some((Token::End, ..)) => {}
+				other => return Err(parser.unexpected(other, "the end of the line")),
+			}
+			// Assigned after the value is parsed, so `a = a + 1` needs `a` already.
+			let variable = parser.variable(&name).unwrap_or_else(|| {
+				parser.variables.push(name);
+				parser.variables.len() - 1
+			});
+			statements.push((variable, expression));
+		}
+
+		Ok(Program {
+			variables: parser.variables,
+			statements,
+		})
+	}
+
+	/// A variable for every name the script uses, to run it with. Inputs go at their index.
+	pub fn variables(&self) -> Vec<f32> {
+		vec![0.0; self.variables.len()]
+	}
+
+	pub fn run(&self, variables: &mut [f32], environment: &dyn Environment) {
+		for (variable, expression) in &self.statements {
+			variables[*variable] = expression.evaluate(variables, environment);
+		}
+	}
+}
+
+impl Expression {
+	fn evaluate(&self, variables: &[f32], environment: &dyn Environment) -> f32 {
+		match self {
+			Expression::Number(value) => *value,
+			Expression::Variable(variable) => variables[*variable],
+			Expression::Negate(operand) => -operand.evaluate(variables, environment),
+			Expression::Binary(operator, a, b) => operator.apply(
+				a.evaluate(variables, environment),
+				b.evaluate(variables, environment),
+			),
+			Expression::Call(function, arguments) => {
+				let mut values = [0.0; 3];
+				for (value, argument) in values.iter_mut().zip(arguments) {
+					*value = argument.evaluate(variables, environment);
+				}
+				function(&values[..arguments.len()])
+			}
+			Expression::Named(function, name) => environment.named(*function, name),
+			Expression::Noise(depth) => environment.noise(depth.evaluate(variables, environment)),
+		}
+	}
+}
+
+impl Operator {
+	fn symbol(self) -> &'static str {
+		match self {
+			Operator::Add => "+",
+			Operator::Subtract => "-",
+			Operator::Multiply => "*",
+			Operator::Divide => "/",
+			Operator::Remainder => "%",
+			Operator::Power => "^",
+			Operator::Less => "<",
+			Operator::LessOrEqual => "<=",
+			Operator::Greater => ">",
+			Operator::GreaterOrEqual => ">=",
+		}
+	}
+}
+
+fn error(line: usize, column: usize, message: String) -> ParseError {
+	ParseError {
+		line,
+		column,
+		message,
+	}
+}
+
+fn tokenize(source: &str) -> Result<Vec<Located>, ParseError> {
+	let mut tokens = vec![];
+	// Line breaks inside brackets don't end the statement.
+	let mut depth = 0usize;
+	for (index, line) in source.lines().enumerate() {
+		let line_number = index + 1;
+		let characters = line.chars().collect::<Vec<_>>();
+		let mut column = 0;
+		while column < characters.len() {
+			let character = characters[column];
+			let start = column;
+			column += 1;
+			let token = match character {
+				'#' => break,
+				_ if character.is_whitespace() => continue,
+				'0'..='9' | '.' => {
+					let digit =
+						|column: usize| characters.get(column).is_some_and(char::is_ascii_digit);
+					while digit(column) || characters.get(column) == Some(&'.') {
+						column += 1;
+					}
+					// An exponent, like the `e-3` of `2.5e-3`.
+					if matches!(characters.get(column), Some('e' | 'E')) {
+						let sign = matches!(characters.get(column + 1), Some('+' | '-')) as usize;
+						if digit(column + 1 + sign) {
+							column += 1 + sign;
+							while digit(column) {
+								column += 1;
+							}
+						}
+					}
+					let text = characters[start..column].iter().collect::<String>();
+					let number = text.parse().map_err(|_| {
+						error(line_number, start + 1, format!("bad number `{text}`"))
+					})?;
+					Token::Number(number)
+				}
+				_ if character.is_alphabetic() || character == '_' => {
+					while column < characters.len()
+						&& (characters[column].is_alphanumeric() || characters[column] == '_')
+					{
+						column += 1;
+					}
+					Token::Name(characters[start..column].iter().collect())
+				}
+				'"' => {
+					let end = (column..characters.len())
+						.find(|&end| characters[end] == '"')
+						.ok_or_else(|| {
+							error(line_number, start + 1, "unclosed string".to_owned())
+						})?;
+					let text = characters[column..end].iter().collect();
+					column = end + 1;
+					Token::Text(text)
+				}
+				'<' | '>' => {
+					let or_equal = characters.get(column) == Some(&'=');
+					if or_equal {
+						column += 1;
+					}
+					Token::Operator(match (character, or_equal) {
+						('<', false) => Operator::Less,
+						('<', true) => Operator::LessOrEqual,
+						('>', false) => Operator::Greater,
+						_ => Operator::GreaterOrEqual,
+					})
+				}
+				'+' => Token::Operator(Operator::Add),
+				'-' => Token::Operator(Operator::Subtract),
+				'*' => Token::Operator(Operator::Multiply),
+				'/' => Token::Operator(Operator::Divide),
+				'%' => Token::Operator(Operator::Remainder),
+				'^' => Token::Operator(Operator::Power),
+				'=' => Token::Assign,
+				'(' => {
+					depth += 1;
+					Token::Open
+				}
+				')' => {
+					depth = depth.saturating_sub(1);
+					Token::Close
+				}
+				',' => Token::Comma,
+				';' => Token::End,
+				_ => {
+					return Err(error(
+						line_number,
+						start + 1,
+						format!("unexpected {character:?}"),
+					))
+				}
+			};
+			tokens.push((token, line_number, start + 1));
+		}
+		if depth == 0 {
+			tokens.push((Token::End, line_number, characters.len() + 1));
+		}
+	}
+	Ok(tokens)
+}
+
+struct Parser {
+	tokens: Vec<Located>,
+	position: usize,
+	/// Where the source ends, for errors about what's missing there.
+	end: (usize, usize),
+	variables: Vec<String>,
+}
+
+impl Parser {
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.position).map(|(token, ..)| token)
+	}
+
+	fn next(&mut self) -> Option<Located> {
+		let token = self.tokens.get(self.position).cloned();
+		self.position += 1;
+		token
+	}
+
+	fn eat(&mut self, token: &Token) -> bool {
+		let matches = self.peek() == Some(token);
+		if matches {
+			self.position += 1;
+		}
+		matches
+	}
+
+	fn variable(&self, name: &str) -> Option<usize> {
+		self.variables.iter().position(|variable| variable == name)
+	}
+
+	fn unexpected(&self, found: Option<Located>, expected: &str) -> ParseError {
+		match found {
+			Some((token, line, column)) => error(
+				line,
+				column,
+				format!("expected {expected}, found {}", describe(&token)),
+			),
+			None => error(
+				self.end.0,
+				self.end.1,
+				format!("expected {expected}, found the end"),
+			),
+		}
+	}
+
+	/// An expression of operators binding tighter than `precedence`.
+	fn expression(&mut self, precedence: u8) -> Result<Expression, ParseError> {
+		let mut left = if self.eat(&Token::Operator(Operator::Subtract)) {
+			Expression::Negate(Box::new(self.expression(NEGATE_PRECEDENCE)?))
+		} else {
+			self.operand()?
+		};
+
+		while let Some(&Token::Operator(operator)) = self.peek() {
+			let (binding, right) = operator.precedence();
+			if binding <= precedence {
+				break;
+			}
+			self.position += 1;
+			let right = self.expression(if right { binding - 1 } else { binding })?;
+			left = Expression::Binary(operator, Box::new(left), Box::new(right));
+		}
+		Ok(left)
+	}
+
+	fn operand(&mut self) -> Result<Expression, ParseError> {
+		match self.next() {
+			Some((Token::Number(value), ..)) => Ok(Expression::Number(value)),
+			Some((Token::Open, ..)) => {
+				let expression = self.expression(0)?;
+				self.close()?;
+				Ok(expression)
+			}
+			Some((Token::Name(name), line, column)) if self.peek() == Some(&Token::Open) => {
+				self.position += 1;
+				self.call(&name, line, column)
+			}
+			Some((Token::Name(name), line, column)) => match self.variable(&name) {
+				Some(variable) => Ok(Expression::Variable(variable)),
+				None => Err(error(line, column, format!("unknown variable `{name}`"))),
+			},
+			other => Err(self.unexpected(other, "a number, variable or `(`")),
+		}
+	}
+
+	fn call(&mut self, name: &str, line: usize, column: usize) -> Result<Expression, ParseError> {
+		if let Some(&(_, function)) = NAMED.iter().find(|&&(named, _)| named == name) {
+			let text = match self.next() {
+				Some((Token::Text(text), ..)) => text,
+				other => return Err(self.unexpected(other, "a name in quotes")),
+			};
+			self.close()?;
+			return Ok(Expression::Named(function, text));
+		}
+
+		let mut arguments = vec![];
+		if !self.eat(&Token::Close) {
+			loop {
+				arguments.push(self.expression(0)?);
+				if self.eat(&Token::Close) {
+					break;
+				}
+				if !self.eat(&Token::Comma) {
+					let next = self.next();
+					return Err(self.unexpected(next, "`,` or `)`"));
+				}
+			}
+		}
+
+		let arity = |expected: usize| {
+			if arguments.len() == expected {
+				Ok(())
+			} else {
+				Err(error(
+					line,
+					column,
+					format!(
+						"`{name}` takes {expected} arguments, not {}",
+						arguments.len()
+					),
+				))
+			}
+		};
+		if name == "noise2" {
+			arity(1)?;
+			return Ok(Expression::Noise(Box::new(arguments.remove(0))));
+		}
+		match FUNCTIONS.iter().find(|&&(function, ..)| function == name) {
+			Some(&(_, expected, function)) => {
+				arity(expected)?;
+				Ok(Expression::Call(function, arguments))
+			}
+			None => Err(error(line, column, format!("unknown function `{name}`"))),
+		}
+	}
+
+	fn close(&mut self) -> Result<(), ParseError> {
+		if self.eat(&Token::Close) {
+			Ok(())
+		} else {
+			let next = self.next();
+			Err(self.unexpected(next, "`)`"))
+		}
+	}
+}
+
+fn describe(token: &Token) -> String {
+	match token {
+		Token::Number(value) => format!("`{value}`"),
+		Token::Name(name) => format!("`{name}`"),
+		Token::Text(text) => format!("{text:?}"),
+		Token::Operator(operator) => format!("`{}`", operator.symbol()),
+		Token::Assign => "`=`".to_owned(),
+		Token::Open => "`(`".to_owned(),
+		Token::Close => "`)`".to_owned(),
+		Token::Comma => "`,`".to_owned(),
+		Token::End => "the end of the line".to_owned(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Reads `band("sub")` as 0.5, every other name as 0, and noise as twice its depth.
+	struct Reads;
+
+	impl Environment for Reads {
+		fn named(&self, function: Named, name: &str) -> f32 {
+			if function == Named::Band && name == "sub" {
+				0.5
+			} else {
+				0.0
+			}
+		}
+
+		fn noise(&self, depth: f32) -> f32 {
+			depth * 2.0
+		}
+	}
+
+	/// Runs `source` with the input `x`, and returns what it leaves in `out`.
+	fn run(source: &str, x: f32) -> f32 {
+		let program = Program::compile(source, &["x", "out"]).unwrap();
+		let mut variables = program.variables();
+		variables[0] = x;
+		program.run(&mut variables, &Reads);
+		variables[1]
+	}
+
+	fn evaluate(expression: &str) -> f32 {
+		run(&format!("out = {expression}"), 3.0)
+	}
+
+	fn compile_error(source: &str) -> ParseError {
+		Program::compile(source, &["x", "out"]).unwrap_err()
+	}
+
+	#[test]
+	fn operators_bind_by_precedence() {
+		assert_eq!(evaluate("-x^2"), -9.0);
+		assert_eq!(evaluate("2^3^2"), 512.0);
+		assert_eq!(evaluate("(2^3)^2"), 64.0);
+		assert_eq!(evaluate("1 + 2 * 3"), 7.0);
+		assert_eq!(evaluate("1 - 2 - 3"), -4.0);
+		assert_eq!(evaluate("8 / 4 / 2"), 1.0);
+		assert_eq!(evaluate("7 % 4 * 2"), 6.0);
+		assert_eq!(evaluate("1 + 2 < 2 * 2"), 1.0);
+		assert_eq!(evaluate("-x^2 + 10 >= 1"), 1.0);
+		assert_eq!(evaluate("x > 3"), 0.0);
+		assert_eq!(evaluate("x <= 3"), 1.0);
+	}
+
+	#[test]
+	fn numbers_may_have_exponents() {
+		assert_eq!(evaluate("1e2"), 100.0);
+		assert_eq!(evaluate("2.5e-1"), 0.25);
+		assert_eq!(evaluate("1.5E+1"), 15.0);
+		assert_eq!(evaluate(".5"), 0.5);
+		assert_eq!(
+			compile_error("out = 2 e"),
+			error(1, 9, "expected the end of the line, found `e`".to_owned())
+		);
+	}
+
+	#[test]
+	fn functions_and_names_read_their_arguments() {
+		assert_eq!(evaluate("max(x, 5) + min(x, 5)"), 8.0);
+		assert_eq!(evaluate("clamp(x, 0, 1)"), 1.0);
+		assert_eq!(evaluate("if(x > 2, 10, 20)"), 10.0);
+		assert_eq!(evaluate("band(\"sub\") + band(\"bass\")"), 0.5);
+		assert_eq!(evaluate("noise2(x)"), 6.0);
+	}
+
+	#[test]
+	fn statements_run_in_order() {
+		assert_eq!(run("a = x * 2\nout = a + 1; out = out * 10", 1.0), 30.0);
+		assert_eq!(run("# A comment.\nout = x # Another.", 4.0), 4.0);
+	}
+
+	#[test]
+	fn brackets_may_span_lines() {
+		assert_eq!(run("out = (1 +\n\t2) * x", 3.0), 9.0);
+		assert_eq!(run("out = max(\n\t1,\n\tx\n)\nout = out + 1", 3.0), 4.0);
+	}
+
+	#[test]
+	fn functions_take_their_number_of_arguments() {
+		assert_eq!(
+			compile_error("out = min(1)"),
+			error(1, 7, "`min` takes 2 arguments, not 1".to_owned())
+		);
+		assert_eq!(
+			compile_error("out = (1 +\n\tsin(1, 2))"),
+			error(2, 2, "`sin` takes 1 arguments, not 2".to_owned())
+		);
+		assert_eq!(
+			compile_error("out = noise2()"),
+			error(1, 7, "`noise2` takes 1 arguments, not 0".to_owned())
+		);
+	}
+
+	#[test]
+	fn unknown_names_are_reported_where_they_are() {
+		assert_eq!(
+			compile_error("a = 1\nout = a + b"),
+			error(2, 11, "unknown variable `b`".to_owned())
+		);
+		assert_eq!(
+			compile_error("out = 1\n  out = cosh(x)"),
+			error(2, 9, "unknown function `cosh`".to_owned())
+		);
+		// Assigned after it's read, so not known yet.
+		assert_eq!(
+			compile_error("a = a + 1"),
+			error(1, 5, "unknown variable `a`".to_owned())
+		);
+		assert_eq!(
+			compile_error("sin = 1"),
+			error(1, 1, "`sin` is a function".to_owned())
+		);
+	}
+
+	#[test]
+	fn unfinished_scripts_are_reported_at_the_end() {
+		assert_eq!(
+			compile_error("out = (1 +\n2"),
+			error(2, 2, "expected `)`, found the end".to_owned())
+		);
+		assert_eq!(
+			compile_error("out = \"sub\""),
+			error(
+				1,
+				7,
+				"expected a number, variable or `(`, found \"sub\"".to_owned()
+			)
+		);
+	}
+}
@@ -1,14 +1,31 @@
 use {
-	crate::scenes::{Area, Scene, *},
+	crate::scenes::{Area, Scene, Transition, *},
 	nannou::{color::luma::Luma, noise::NoiseFn, prelude::*},
+	std::borrow::Cow,
+};
+
+pub const SECTION: Section = Section {
+	name: "for_against",
+	title: "For / Against",
+	words: &["for", "against"],
+	order: 0,
+	enabled: true,
+	scenes: SCENES,
 };
 
 pub const SCENES: &[&Scene] = &[
 	&Scene {
-		path: "/bu/2.mp3",
+		title: Cow::Borrowed(""),
+		path: Cow::Borrowed("/bu/2.mp3"),
 		loop_: true,
 		measures: 8,
-		render: &|draw, model, i, j, time, modifiers| {
+		transition: Transition::Cut,
+		bands: None,
+		waveform: 0,
+		chroma: false,
+		pattern: None,
+		envelopes: Cow::Borrowed(&[]),
+		render: Render::Compiled(&|draw, model, i, j, time, modifiers| {
 			let noise = model.noisemap.get_value(i, j) as f32;
 			let (i, j) = scale_coordinates(model, i, j);
 			let gray = noise;
@@ -17,20 +34,37 @@ pub const SCENES: &[&Scene] = &[
 			draw.line()
 				.gray(gray)
 				.points(Point2::new(i, j), Point2::new(i + 1.0, j + 1.0));
-		},
-		boxes: &[Area {
+		}),
+		boxes: Cow::Borrowed(&[Area {
 			x0: -100.0,
 			y0: -100.0,
 			x1: 100.0,
 			y1: 100.0,
-		}],
+		}]),
 	},
 	&Scene {
-		path: "/bu/2.mp3",
+		title: Cow::Borrowed(""),
+		path: Cow::Borrowed("/bu/2.mp3"),
 		loop_: true,
 		measures: 8,
-		render: &|draw, model, i, j, time, modifiers| {
-			let [whole, half, quarter, eighth, sixteenth, bass, mid, treble] = modifiers;
+		transition: Transition::Cut,
+		bands: None,
+		waveform: 0,
+		chroma: false,
+		pattern: None,
+		envelopes: Cow::Borrowed(&[]),
+		render: Render::Compiled(&|draw, model, i, j, time, modifiers| {
+			let Modifiers {
+				whole,
+				half,
+				quarter,
+				eighth,
+				sixteenth,
+				bass,
+				mid,
+				treble,
+				..
+			} = *modifiers;
 			let noise = model.noisemap.get_value(i, j) as f32;
 			let noise2 = model
 				.noise_matrix
@@ -58,20 +92,37 @@ pub const SCENES: &[&Scene] = &[
 			draw.line()
 				.gray(gray)
 				.points(Point2::new(i0, j0), Point2::new(i1, j1));
-		},
-		boxes: &[Area {
+		}),
+		boxes: Cow::Borrowed(&[Area {
 			x0: -100.0,
 			y0: -100.0,
 			x1: 100.0,
 			y1: 100.0,
-		}],
+		}]),
 	},
 	&Scene {
-		path: "/fa/1.mp3",
+		title: Cow::Borrowed(""),
+		path: Cow::Borrowed("/fa/1.mp3"),
 		loop_: true,
 		measures: 14,
-		render: &|draw, model, i, j, time, modifiers| {
-			let [whole, half, quarter, eighth, sixteenth, bass, mid, treble] = modifiers;
+		transition: Transition::Crossfade(4.0),
+		bands: None,
+		waveform: 0,
+		chroma: false,
+		pattern: None,
+		envelopes: Cow::Borrowed(&[]),
+		render: Render::Compiled(&|draw, model, i, j, time, modifiers| {
+			let Modifiers {
+				whole,
+				half,
+				quarter,
+				eighth,
+				sixteenth,
+				bass,
+				mid,
+				treble,
+				..
+			} = *modifiers;
 			let noise = model.noisemap.get_value(i, j) as f32;
 			// let noise2 = model
 			// 	.noise_matrix
@@ -88,20 +139,37 @@ pub const SCENES: &[&Scene] = &[
 			draw.line()
 				.gray(gray)
 				.points(Point2::new(i0, j0), Point2::new(i1, j1));
-		},
-		boxes: &[Area {
+		}),
+		boxes: Cow::Borrowed(&[Area {
 			x0: -100.0,
 			y0: -100.0,
 			x1: 100.0,
 			y1: 100.0,
-		}],
+		}]),
 	},
 	&Scene {
-		path: "/fa/1.mp3",
+		title: Cow::Borrowed(""),
+		path: Cow::Borrowed("/fa/1.mp3"),
 		loop_: true,
 		measures: 14,
-		render: &|draw, model, i, j, time, modifiers| {
-			let [whole, half, quarter, eighth, sixteenth, bass, mid, treble] = modifiers;
+		transition: Transition::Cut,
+		bands: None,
+		waveform: 0,
+		chroma: false,
+		pattern: None,
+		envelopes: Cow::Borrowed(&[]),
+		render: Render::Compiled(&|draw, model, i, j, time, modifiers| {
+			let Modifiers {
+				whole,
+				half,
+				quarter,
+				eighth,
+				sixteenth,
+				bass,
+				mid,
+				treble,
+				..
+			} = *modifiers;
 			let noise = model.noisemap.get_value(i, j) as f32;
 			let noise2 = model
 				.noise_matrix
@@ -134,8 +202,8 @@ pub const SCENES: &[&Scene] = &[
 			draw.line()
 				.gray(gray)
 				.points(Point2::new(i0, j0), Point2::new(i1, j1));
-		},
-		boxes: &[
+		}),
+		boxes: Cow::Borrowed(&[
 			Area {
 				x0: -5.0,
 				y0: -5.0,
@@ -148,14 +216,31 @@ pub const SCENES: &[&Scene] = &[
 				x1: 0.0,
 				y1: 0.0,
 			},
-		],
+		]),
 	},
 	&Scene {
-		path: "/fa/2.mp3",
+		title: Cow::Borrowed(""),
+		path: Cow::Borrowed("/fa/2.mp3"),
 		loop_: true,
-		measures: 14,
-		render: &|draw, model, i, j, time, modifiers| {
-			let [whole, half, quarter, eighth, sixteenth, bass, mid, treble] = modifiers;
+		measures: 8,
+		transition: Transition::Crossfade(4.0),
+		bands: None,
+		waveform: 0,
+		chroma: false,
+		pattern: None,
+		envelopes: Cow::Borrowed(&[]),
+		render: Render::Compiled(&|draw, model, i, j, time, modifiers| {
+			let Modifiers {
+				whole,
+				half,
+				quarter,
+				eighth,
+				sixteenth,
+				bass,
+				mid,
+				treble,
+				..
+			} = *modifiers;
 			let noise = model.noisemap.get_value(i, j) as f32;
 			let noise2 = model
 				.noise_matrix
@@ -181,8 +266,8 @@ pub const SCENES: &[&Scene] = &[
 			draw.line()
 				.gray(gray)
 				.points(Point2::new(i0, j0), Point2::new(i1, j1));
-		},
-		boxes: &[
+		}),
+		boxes: Cow::Borrowed(&[
 			Area {
 				x0: -5.0,
 				y0: -5.0,
@@ -195,14 +280,31 @@ pub const SCENES: &[&Scene] = &[
 				x1: 0.0,
 				y1: 0.0,
 			},
-		],
+		]),
 	},
 	&Scene {
-		path: "/fa/2.mp3",
+		title: Cow::Borrowed(""),
+		path: Cow::Borrowed("/fa/2.mp3"),
 		loop_: true,
-		measures: 14,
-		render: &|draw, model, i, j, time, modifiers| {
-			let [whole, half, quarter, eighth, sixteenth, bass, mid, treble] = modifiers;
+		measures: 8,
+		transition: Transition::Cut,
+		bands: None,
+		waveform: 0,
+		chroma: false,
+		pattern: None,
+		envelopes: Cow::Borrowed(&[]),
+		render: Render::Compiled(&|draw, model, i, j, time, modifiers| {
+			let Modifiers {
+				whole,
+				half,
+				quarter,
+				eighth,
+				sixteenth,
+				bass,
+				mid,
+				treble,
+				..
+			} = *modifiers;
 			let noise = model.noisemap.get_value(i, j) as f32;
 			let noise2 = model.noise_matrix.get([i as f64, j as f64, time as f64]) as f32;
 
@@ -235,8 +337,8 @@ pub const SCENES: &[&Scene] = &[
 			draw.line()
 				.gray(gray)
 				.points(Point2::new(i0, j0), Point2::new(i1, j1));
-		},
-		boxes: &[
+		}),
+		boxes: Cow::Borrowed(&[
 			Area {
 				x0: 5.0,
 				y0: 5.0,
@@ -249,14 +351,21 @@ pub const SCENES: &[&Scene] = &[
 				x1: 0.0,
 				y1: 0.0,
 			},
-		],
+		]),
 	},
 	// &Scene {
-	// 	path: "/fa/2.mp3",
+	// 	title: Cow::Borrowed(""),
+	// 	path: Cow::Borrowed("/fa/2.mp3"),
 	// 	loop_: true,
 	// 	measures: 8,
-	// 	render: &|draw, model, i, j, time| {
-	// 		let [whole, half, quarter, eighth, sixteenth, bass, mid, treble] = get_modifiers(model);
+	// 	transition: Transition::Cut,
+	// 	bands: None,
+	// 	waveform: 0,
+	// 	chroma: false,
+	// 	pattern: None,
+	// 	envelopes: Cow::Borrowed(&[]),
+	// 	render: Render::Compiled(&|draw, model, i, j, time, modifiers| {
+	// 		let Modifiers { whole, half, quarter, eighth, sixteenth, bass, mid, treble, .. } = *modifiers;
 
 	// 		let noise = model.noisemap.get_value(i, j) as f32;
 	// 		let noise2 = model
@@ -292,7 +401,7 @@ pub const SCENES: &[&Scene] = &[
 	// 		draw.line()
 	// 			.gray(gray)
 	// 			.points(Point2::new(i0, j0), Point2::new(i1, j1));
-	// 	},
+	// 	}),
 	// 	boxes: [
 	// 		Area {
 	// 			x0: 0.0.0,
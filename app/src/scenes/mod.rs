@@ -1,13 +1,31 @@
 use {
-	crate::model::{Model, CANVAS_HEIGHT, CANVAS_WIDTH},
+	crate::{
+		analysis::{Bands, Envelope},
+		model::{Model, CANVAS_HEIGHT, CANVAS_WIDTH},
+	},
 	nannou::Draw,
+	serde::Deserialize,
+	std::{borrow::Cow, ops::Deref, rc::Rc},
 };
 
 // pub mod being_useless;
+mod definition;
+mod expression;
 pub mod for_against;
+mod modifiers;
 // pub mod porcelain_piss;
+mod registry;
+mod studies;
 pub mod terra_firmament;
 
+pub use {
+	definition::{parse as parse_scenes, SCENES_PATH},
+	modifiers::{get_modifiers, Modifiers},
+	registry::{Album, Registry, Section, ALBUM},
+};
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Area {
 	x0: f32,
 	y0: f32,
@@ -22,60 +40,71 @@ fn scale_coordinates(model: &Model, i: usize, j: usize) -> (f32, f32) {
 	)
 }
 
-fn u8_to_f32(n: u8) -> f32 {
-	n as f32 / 255.0
+impl Area {
+	pub fn inside(&self, x: f32, y: f32) -> bool {
+		self.x0 < x && x < self.x1 && self.y0 < y && y < self.y1
+	}
 }
 
-pub fn get_modifiers(model: &Model) -> [f32; 8] {
-	let half = (model.last_whole as f32 % 0.5) * 2.0;
-	let quarter = (half % 0.5) * 2.0;
-	let eighth = (quarter % 0.5) * 2.0;
-	let sixteenth = (eighth % 0.5) * 2.0;
-
-	let mut fft = [0; 16];
-	model.audio.analyser.get_byte_frequency_data(&mut fft);
+/// How a scene takes over from the one before it, on a bar line either way. Scenes playing the
+/// same track as the one before carry on with it instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+pub enum Transition {
+	/// The previous track stops on the downbeat the next one starts on.
+	#[default]
+	Cut,
+	/// Both tracks play for this many beats of the previous one, fading across.
+	Crossfade(f64),
+}
 
-	let bass = (u8_to_f32(fft[0]) + u8_to_f32(fft[1])) as f32 / 2.0;
-	let mid = (u8_to_f32(fft[2])
-		+ u8_to_f32(fft[3])
-		+ u8_to_f32(fft[4])
-		+ u8_to_f32(fft[5])
-		+ u8_to_f32(fft[6])
-		+ u8_to_f32(fft[7])) as f32
-		/ 6.0;
-	let treble = (u8_to_f32(fft[8])
-		+ u8_to_f32(fft[9])
-		+ u8_to_f32(fft[10])
-		+ u8_to_f32(fft[11])
-		+ u8_to_f32(fft[12])
-		+ u8_to_f32(fft[13])
-		+ u8_to_f32(fft[14])
-		+ u8_to_f32(fft[15])) as f32
-		/ 8.0;
+/// Draws the line at column `i` and row `j` of the grid.
+pub type RenderFn = dyn Fn(&Draw, &Model, usize, usize, f32, &Modifiers);
 
-	[
-		model.last_whole as f32,
-		half,
-		quarter,
-		eighth,
-		sixteenth,
-		bass,
-		mid,
-		treble,
-	]
+/// A scene's drawing, compiled in or built from the scene list.
+#[derive(Clone)]
+pub enum Render {
+	Compiled(&'static RenderFn),
+	Defined(Rc<RenderFn>),
 }
 
-impl Area {
-	pub fn inside(&self, x: f32, y: f32) -> bool {
-		self.x0 < x && x < self.x1 && self.y0 < y && y < self.y1
+impl Deref for Render {
+	type Target = RenderFn;
+
+	fn deref(&self) -> &RenderFn {
+		match self {
+			Render::Compiled(render) => *render,
+			Render::Defined(render) => &**render,
+		}
 	}
 }
 
+/// Compiled scenes borrow everything, scenes from the scene list own it, so reloading the list
+/// lets go of the last version.
+#[derive(Clone)]
 pub struct Scene {
+	/// What the HUD calls it, or empty for the HUD to show its track instead.
+	pub title: Cow<'static, str>,
 	pub loop_: bool,
-	pub path: &'static str,
+	pub path: Cow<'static, str>,
 	pub measures: usize,
+	pub transition: Transition,
+	/// How the spectrum is split up for this scene, `Bands::DEFAULT` if `None`.
+	pub bands: Option<Cow<'static, Bands>>,
+	/// Samples of waveform the scene gets, none if 0.
+	pub waveform: usize,
+	/// Whether the scene reads `chroma` and `pitch_class`, which take a long FFT every frame.
+	pub chroma: bool,
+	/// Where the server hosts the pattern of steps the scene hits in time with, if it has one.
+	pub pattern: Option<Cow<'static, str>>,
+	/// Signals to follow, see `Modifiers::signal`, and how.
+	pub envelopes: Cow<'static, [(Cow<'static, str>, Envelope)]>,
 
-	pub render: &'static dyn Fn(&Draw, &Model, usize, usize, f32, [f32; 8]),
-	pub boxes: &'static [Area],
+	pub render: Render,
+	pub boxes: Cow<'static, [Area]>,
+}
+
+impl Scene {
+	pub fn bands(&self) -> &Bands {
+		self.bands.as_deref().unwrap_or(&Bands::DEFAULT)
+	}
 }
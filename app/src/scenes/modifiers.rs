@@ -0,0 +1,389 @@
+use {
+	crate::{
+		analysis::{self, Envelope, Follower, OnsetDetector, CHROMA_SIZE},
+		midi::Note,
+		model::{Model, CANVAS_WIDTH},
+		transport::TICKS_PER_BEAT,
+	},
+	nannou::prelude::*,
+	std::borrow::Cow,
+};
+
+/// Seconds chroma is smoothed over.
+const CHROMA_SMOOTHING: f32 = 0.3;
+/// Spectrum RMS below which there is no pitch class to speak of.
+const SILENCE: f32 = 0.01;
+
+/// Everything scenes react to, worked out once per frame. Most values are between 0 and 1.
+#[derive(Clone, Debug, Default)]
+pub struct Modifiers {
+	/// How far through the bar, the half bar, and so on down to the sixteenth.
+	pub whole: f32,
+	pub half: f32,
+	pub quarter: f32,
+	pub eighth: f32,
+	pub sixteenth: f32,
+
+	/// The bands of the same names, if the scene has them.
+	pub bass: f32,
+	pub mid: f32,
+	pub treble: f32,
+	/// Every band the scene declared, in order.
+	pub bands: Vec<(String, f32)>,
+	/// The same bands of just the left channel, and of just the right.
+	pub left: Vec<(String, f32)>,
+	pub right: Vec<(String, f32)>,
+	/// Where the sound sits, from -1 all left through 0 in the middle to 1 all right.
+	pub balance: f32,
+	/// How much the channels differ, from 0 for mono to 1 for nothing in common.
+	pub width: f32,
+	/// Root mean square of the spectrum, a rough loudness.
+	pub rms: f32,
+	/// Where the weight of the spectrum sits, from the lowest bin at 0 to the highest at 1.
+	pub centroid: f32,
+	/// How much louder the spectrum got since the last frame.
+	pub flux: f32,
+	/// Onset strength from the track's analysis, or the flux if it hasn't been analysed.
+	pub onsets: f32,
+	/// Mouse position, from -1 to 1 across the window either way.
+	pub mouse: Vec2,
+
+	pub spectrum: Vec<f32>,
+	/// The latest samples, oldest first, as many as the scene asked for.
+	pub waveform: Vec<f32>,
+	/// Energy in each pitch class, from C up, over the last fraction of a second. The loudest
+	/// is 1. All zeros unless the scene asks for `Scene::chroma`.
+	pub chroma: [f32; 12],
+	/// The loudest pitch class, 0 for C, `None` in silence or if the scene doesn't ask.
+	pub pitch_class: Option<usize>,
+	/// Velocity of each lane of the scene's pattern at the step playing.
+	pub step: Vec<(String, f32)>,
+	/// Lanes of the pattern hit by a step that started this frame, at the loudest velocity.
+	pub hits: Vec<(String, f32)>,
+	/// Notes of the track's MIDI file that started since the last frame.
+	pub notes: Vec<Note>,
+	/// Notes of the track's MIDI file sounding now.
+	pub held: Vec<Note>,
+	/// Bands with an onset this frame, and how strong it is.
+	pub triggers: Vec<(String, f32)>,
+	/// Followers for the signals the scene asked to have smoothed, by signal name.
+	pub envelopes: Vec<(String, Follower)>,
+
+	detectors: Vec<(String, OnsetDetector)>,
+	/// The track notes were last read from, and how far into it.
+	position: (String, f64),
+}
+
+impl Modifiers {
+	/// The band called `name`, 0 if the scene has none.
+	pub fn band(&self, name: &str) -> f32 {
+		band(&self.bands, name)
+	}
+
+	/// Where the band called `name` sits, from -1 all left to 1 all right, 0 if it's silent or
+	/// the scene has no such band.
+	pub fn pan(&self, name: &str) -> f32 {
+		pan(band(&self.left, name), band(&self.right, name))
+	}
+
+	/// The waveform stretched across the canvas: the sample under the middle of column `i`.
+	pub fn waveform_column(&self, i: usize) -> f32 {
+		let Some(last) = self.waveform.len().checked_sub(1) else {
+			return 0.0;
+		};
+		let position = (i as f32 + 0.5) / CANVAS_WIDTH as f32 * last as f32;
+		let (index, fraction) = (position as usize, position.fract());
+		let next = self.waveform.get(index + 1).unwrap_or(&self.waveform[last]);
+		self.waveform[index.min(last)] * (1.0 - fraction) + next * fraction
+	}
+
+	/// Velocity of the pattern's lane called `name` at the step playing, 0 if it has none.
+	pub fn lane(&self, name: &str) -> f32 {
+		(self.step.iter())
+			.find(|(lane, _)| lane == name)
+			.map_or(0.0, |&(_, velocity)| velocity)
+	}
+
+	/// Velocity of the hit on the pattern's lane called `name` this frame, if there was one.
+	pub fn hit(&self, name: &str) -> Option<f32> {
+		(self.hits.iter())
+			.find(|(lane, _)| lane == name)
+			.map(|&(_, velocity)| velocity)
+	}
+
+	/// Velocity of the loudest note that started on `channel` since the last frame, if one did.
+	pub fn note(&self, channel: u8) -> Option<f32> {
+		(self.notes.iter())
+			.filter(|note| note.channel == channel)
+			.map(|note| note.velocity)
+			.reduce(f32::max)
+	}
+
+	/// The strength of the onset the band called `name` had this frame, if it had one.
+	pub fn triggered(&self, name: &str) -> Option<f32> {
+		(self.triggers.iter())
+			.find(|(band, _)| band == name)
+			.map(|&(_, strength)| strength)
+	}
+
+	/// A band, one of `rms`, `centroid`, `flux`, `onsets` or `width`, `onset:` and a band for that
+	/// band's trigger strength this frame, `hit:` and a lane for the velocity it was hit at this
+	/// frame, or `note:` and a MIDI channel for the velocity of the loudest note started on it,
+	/// by name. Following a trigger, hit or note stretches it out into a flash.
+	pub fn signal(&self, name: &str) -> f32 {
+		match name {
+			"rms" => self.rms,
+			"centroid" => self.centroid,
+			"flux" => self.flux,
+			"onsets" => self.onsets,
+			"width" => self.width,
+			_ => {
+				if let Some(band) = name.strip_prefix("onset:") {
+					self.triggered(band).unwrap_or(0.0)
+				} else if let Some(lane) = name.strip_prefix("hit:") {
+					self.hit(lane).unwrap_or(0.0)
+				} else if let Some(channel) = name.strip_prefix("note:") {
+					(channel.parse().ok())
+						.and_then(|channel| self.note(channel))
+						.unwrap_or(0.0)
+				} else {
+					self.band(name)
+				}
+			}
+		}
+	}
+
+	/// The follower of the signal called `name`, if the scene asked for one.
+	pub fn envelope(&self, name: &str) -> Option<&Follower> {
+		(self.envelopes.iter())
+			.find(|(signal, _)| signal == name)
+			.map(|(_, follower)| follower)
+	}
+}
+
+fn band(bands: &[(String, f32)], name: &str) -> f32 {
+	(bands.iter())
+		.find(|(band, _)| band == name)
+		.map_or(0.0, |&(_, value)| value)
+}
+
+fn pan(left: f32, right: f32) -> f32 {
+	if left + right > 0.0 {
+		(right - left) / (right + left)
+	} else {
+		0.0
+	}
+}
+
+/// Reads this frame's modifiers off the model, `dt` seconds after the last ones. `mouse` is in
+/// window coordinates.
+pub fn get_modifiers(model: &Model, window: Rect, mouse: Point2, dt: f32) -> Modifiers {
+	let beat = model.last_beat as f32;
+	let half = (model.last_whole as f32 * 2.0) % 1.0;
+	let quarter = beat % 1.0;
+	let eighth = (quarter % 0.5) * 2.0;
+	let sixteenth = (eighth % 0.5) * 2.0;
+
+	let config = model.current_scene().bands();
+	let mut fft = vec![0; config.frequency_bin_count()];
+	model.frequency_data(&mut fft);
+	let spectrum = fft
+		.iter()
+		.map(|&value| value as f32 / 255.0)
+		.collect::<Vec<_>>();
+	let sample_rate = model.audio.sample_rate();
+	// Chroma and the waveform share one read of the latest samples, only as long as the scene
+	// needs.
+	let scene = model.current_scene();
+	let length = scene.waveform;
+	let chroma_size = if scene.chroma { CHROMA_SIZE } else { 0 };
+	let mut samples = vec![0.0; length.max(chroma_size)];
+	model.audio.time_domain_data(&mut samples);
+	let waveform = samples[samples.len() - length..].to_vec();
+	let chroma = if scene.chroma {
+		smooth_chroma(
+			&model.modifiers.chroma,
+			analysis::chroma(&samples[samples.len() - CHROMA_SIZE..], sample_rate),
+			dt,
+		)
+	} else {
+		[0.0; 12]
+	};
+	let average = |spectrum: &[f32]| {
+		(config.bands.iter())
+			.zip(config.average(spectrum, sample_rate))
+			.map(|(band, value)| (band.name.to_string(), value))
+			.collect::<Vec<_>>()
+	};
+	let bands = average(&spectrum);
+
+	let [left, right] = [0, 1].map(|channel| {
+		model.audio.channel_frequency_data(channel, &mut fft);
+		fft.iter()
+			.map(|&value| value as f32 / 255.0)
+			.collect::<Vec<_>>()
+	});
+	let balance = pan(left.iter().sum(), right.iter().sum());
+	let difference = (left.iter().zip(&right))
+		.map(|(left, right)| (left - right).abs())
+		.sum::<f32>();
+	let energy = left.iter().chain(&right).sum::<f32>();
+	let width = if energy > 0.0 {
+		difference / energy
+	} else {
+		0.0
+	};
+	let (left, right) = (average(&left), average(&right));
+
+	let (mut step, mut hits): (_, Vec<(String, f32)>) = (vec![], vec![]);
+	if let Some(pattern) = model.pattern() {
+		let ticks_per_bar = TICKS_PER_BEAT * model.grid().beats_per_bar as i64;
+		let tick = model.last_bar * ticks_per_bar
+			+ (model.last_beat * TICKS_PER_BEAT as f64).floor() as i64;
+		step = (pattern.step(tick))
+			.map(|(lane, velocity)| (lane.to_owned(), velocity))
+			.collect();
+
+		// Every tick since the last frame, so steps between frames still hit.
+		for event in model.ticks() {
+			let tick =
+				event.bar * ticks_per_bar + event.beat as i64 * TICKS_PER_BEAT + event.tick as i64;
+			for (lane, velocity) in pattern.step(tick).filter(|&(_, velocity)| velocity > 0.0) {
+				match hits.iter_mut().find(|(hit, _)| hit == lane) {
+					Some((_, loudest)) => *loudest = velocity.max(*loudest),
+					None => hits.push((lane.to_owned(), velocity)),
+				}
+			}
+		}
+	}
+
+	// Notes from where the last frame left off. Looping tracks wrap around, and a new track's
+	// notes count from the frame after it starts.
+	let path = &model.current_scene().path;
+	let time = model.current_time();
+	let (mut notes, mut held) = (vec![], vec![]);
+	if let Some(timeline) = model.timeline() {
+		let (last_path, last) = &model.modifiers.position;
+		let last = *last;
+		notes = if last_path != path {
+			vec![]
+		} else if last <= time {
+			timeline.started(last, time).to_vec()
+		} else {
+			let (end, start) = (f64::INFINITY, f64::NEG_INFINITY);
+			[timeline.started(last, end), timeline.started(start, time)].concat()
+		};
+		held = timeline.held(time).copied().collect();
+	}
+
+	let mut triggers = vec![];
+	let detectors = (config.bands.iter())
+		.zip(config.ranges(spectrum.len(), sample_rate))
+		.map(|(band, range)| {
+			let mut detector = (model.modifiers.detectors.iter())
+				.find(|(name, _)| *name == band.name)
+				.map_or_else(OnsetDetector::new, |(_, detector)| detector.clone());
+			if let Some(strength) = detector.update(&spectrum[range], dt) {
+				triggers.push((band.name.to_string(), strength));
+			}
+			(band.name.to_string(), detector)
+		})
+		.collect();
+
+	let total = spectrum.iter().sum::<f32>();
+	let centroid = if total > 0.0 {
+		let weighted = (spectrum.iter().enumerate())
+			.map(|(bin, value)| bin as f32 * value)
+			.sum::<f32>();
+		weighted / total / (spectrum.len() - 1) as f32
+	} else {
+		0.0
+	};
+	// Spectra of different sizes can't be compared bin for bin.
+	let last = &model.modifiers.spectrum;
+	let flux = if last.len() == spectrum.len() {
+		(spectrum.iter().zip(last))
+			.map(|(value, last)| (value - last).max(0.0))
+			.sum::<f32>()
+			/ spectrum.len() as f32
+	} else {
+		0.0
+	};
+	let rms =
+		(spectrum.iter().map(|value| value * value).sum::<f32>() / spectrum.len() as f32).sqrt();
+
+	let mut modifiers = Modifiers {
+		whole: model.last_whole as f32,
+		half,
+		quarter,
+		eighth,
+		sixteenth,
+		bass: band(&bands, "bass"),
+		mid: band(&bands, "mid"),
+		treble: band(&bands, "treble"),
+		bands,
+		left,
+		right,
+		balance,
+		width,
+		rms,
+		centroid,
+		flux,
+		onsets: model.onset().unwrap_or(flux),
+		mouse: mouse / (window.wh() / 2.0),
+		spectrum,
+		waveform,
+		chroma,
+		pitch_class: (rms > SILENCE)
+			.then(|| analysis::dominant_pitch_class(&chroma))
+			.flatten(),
+		step,
+		hits,
+		notes,
+		held,
+		triggers,
+		envelopes: vec![],
+		detectors,
+		position: (path.to_string(), time),
+	};
+	modifiers.envelopes = follow(
+		&model.current_scene().envelopes,
+		&model.modifiers.envelopes,
+		&modifiers,
+		dt,
+	);
+	modifiers
+}
+
+/// Eases the chroma of the last frames towards this frame's, since harmony changes a lot slower
+/// than a frame.
+fn smooth_chroma(last: &[f32; 12], chroma: [f32; 12], dt: f32) -> [f32; 12] {
+	let weight = 1.0 - (-dt / CHROMA_SMOOTHING).exp();
+	let mut smoothed = *last;
+	for (smoothed, value) in smoothed.iter_mut().zip(chroma) {
+		*smoothed += (value - *smoothed) * weight;
+	}
+	smoothed
+}
+
+/// Steps a follower for each signal in `envelopes`, carrying on from the `last` frame's where
+/// they were following the same signal the same way.
+fn follow(
+	envelopes: &[(Cow<'static, str>, Envelope)],
+	last: &[(String, Follower)],
+	modifiers: &Modifiers,
+	dt: f32,
+) -> Vec<(String, Follower)> {
+	(envelopes.iter())
+		.map(|(name, envelope)| {
+			let mut follower = (last.iter())
+				.find(|(signal, follower)| signal == name && follower.envelope == *envelope)
+				.map_or_else(
+					|| Follower::new(*envelope),
+					|(_, follower)| follower.clone(),
+				);
+			follower.update(modifiers.signal(name), dt);
+			(name.to_string(), follower)
+		})
+		.collect()
+}
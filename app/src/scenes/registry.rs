@@ -0,0 +1,130 @@
+//! The work as a whole: an album of titled sections, each of scenes. Every section module
+//! registers itself with a `Section` and is listed in `ALBUM`; which ones play is up to their
+//! `enabled` flags, or to a comma separated list of section names from, in order of preference:
+//!
+//! - the `sections` query parameter of the page's URL, like `?sections=for_against`, on the web,
+//! - the `SECTIONS` environment variable when the native build runs,
+//! - the `SECTIONS` environment variable when the app was built.
+
+use {
+	super::{for_against, studies, terra_firmament, Scene},
+	std::{ptr, rc::Rc},
+};
+
+pub struct Album {
+	pub title: &'static str,
+	pub sections: &'static [&'static Section],
+}
+
+pub struct Section {
+	/// What the section is selected by, its module's name.
+	pub name: &'static str,
+	pub title: &'static str,
+	/// The words the section sets against each other, from `words::paired_words`.
+	pub words: &'static [&'static str],
+	/// Sections play from the lowest `order` to the highest.
+	pub order: i32,
+	/// Whether it plays when no sections are selected.
+	pub enabled: bool,
+	pub scenes: &'static [&'static Scene],
+}
+
+pub const ALBUM: Album = Album {
+	title: "Gender Theory",
+	sections: &[
+		&for_against::SECTION,
+		&terra_firmament::SECTION,
+		&studies::SECTION,
+	],
+};
+
+/// The sections in the order they play, whether each does, and their scenes. Clones share the
+/// scenes, so a scene from any of them is found in its section by address.
+#[derive(Clone)]
+pub struct Registry {
+	sections: Vec<(&'static Section, bool, Vec<Rc<Scene>>)>,
+}
+
+impl Registry {
+	/// Plays the sections `selection` names, if it names any that have scenes, and the enabled
+	/// ones otherwise.
+	pub fn new(selection: Option<&str>) -> Self {
+		let mut sections = ALBUM.sections.to_vec();
+		sections.sort_by_key(|section| section.order);
+		let selected = |section: &Section| {
+			selection.is_some_and(|names| names.split(',').any(|name| name.trim() == section.name))
+		};
+		let any_selected =
+			(sections.iter()).any(|section| selected(section) && !section.scenes.is_empty());
+		Registry {
+			sections: (sections.into_iter())
+				.map(|section| {
+					let plays = if any_selected {
+						selected(section)
+					} else {
+						section.enabled
+					};
+					let scenes = (section.scenes.iter())
+						.map(|&scene| Rc::new(scene.clone()))
+						.collect();
+					(section, plays, scenes)
+				})
+				.collect(),
+		}
+	}
+
+	/// Plays the sections selected where the app runs, see above.
+	pub fn from_environment() -> Self {
+		Self::new(selection().as_deref())
+	}
+
+	/// Every compiled scene in order, playing or not. These are what `Compiled` entries of the
+	/// scene list count.
+	pub fn compiled(&self) -> Vec<Rc<Scene>> {
+		(self.sections.iter())
+			.flat_map(|(_, _, scenes)| scenes.iter().cloned())
+			.collect()
+	}
+
+	/// The scenes of the sections that play, or of all of them if none of those have any.
+	pub fn scenes(&self) -> Vec<Rc<Scene>> {
+		let scenes = (self.sections.iter())
+			.filter(|(_, plays, _)| *plays)
+			.flat_map(|(_, _, scenes)| scenes.iter().cloned())
+			.collect::<Vec<_>>();
+		if scenes.is_empty() {
+			self.compiled()
+		} else {
+			scenes
+		}
+	}
+
+	/// The section `scene` is compiled into, if it is.
+	pub fn section_of(&self, scene: &Scene) -> Option<&'static Section> {
+		(self.sections.iter())
+			.find(|(_, _, scenes)| scenes.iter().any(|other| ptr::eq(&**other, scene)))
+			.map(|&(section, _, _)| section)
+	}
+
+	/// Whether `scene` plays: it does unless it's compiled into a section that doesn't.
+	pub fn plays(&self, scene: &Scene) -> bool {
+		!(self.sections.iter())
+			.any(|(_, plays, scenes)| !plays && scenes.iter().any(|other| ptr::eq(&**other, scene)))
+	}
+}
+
+#[cfg(target_arch = "wasm32")]
+fn selection() -> Option<String> {
+	let from_url = || {
+		let search = web_sys::window()?.location().search().ok()?;
+		web_sys::UrlSearchParams::new_with_str(&search)
+			.ok()?
+			.get("sections")
+	};
+	from_url().or_else(|| option_env!("SECTIONS").map(str::to_owned))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn selection() -> Option<String> {
+	(std::env::var("SECTIONS").ok()).or_else(|| option_env!("SECTIONS").map(str::to_owned))
+}
@@ -0,0 +1,231 @@
+//! Sketches of what scenes can react to, each reading one feature the show doesn't lean on. They
+//! only play when selected, with `studies`.
+
+use {
+	crate::{
+		analysis::{Band, Bands, Envelope, Follower, Unit},
+		scenes::{Area, Scene, Transition, *},
+	},
+	nannou::prelude::*,
+	std::{
+		borrow::Cow,
+		f32::consts::{FRAC_PI_2, FRAC_PI_4, TAU},
+	},
+};
+
+/// The `/fa` tracks are mostly low end, which the default bands lump into `bass`.
+const LOW_END: Bands = Bands {
+	fft_size: 2048,
+	unit: Unit::Hertz,
+	bands: Cow::Borrowed(&[
+		Band {
+			name: Cow::Borrowed("sub"),
+			low: 20.0,
+			high: 60.0,
+		},
+		Band {
+			name: Cow::Borrowed("bass"),
+			low: 60.0,
+			high: 250.0,
+		},
+		Band {
+			name: Cow::Borrowed("mid"),
+			low: 250.0,
+			high: 2000.0,
+		},
+		Band {
+			name: Cow::Borrowed("treble"),
+			low: 2000.0,
+			high: 16000.0,
+		},
+	]),
+};
+
+pub const SECTION: Section = Section {
+	name: "studies",
+	title: "Studies",
+	words: &[],
+	order: 2,
+	enabled: false,
+	scenes: SCENES,
+};
+
+/// Clicking the middle of the grid moves on.
+const MIDDLE: Area = Area {
+	x0: -5.0,
+	y0: -5.0,
+	x1: 5.0,
+	y1: 5.0,
+};
+
+/// Draws the line every study draws at column `i` and row `j`, so that they differ only in what
+/// they read: `gray`, and `length` long at `angle` from the grid point.
+fn stroke(draw: &Draw, model: &Model, (i, j): (usize, usize), gray: f32, angle: f32, length: f32) {
+	let (x, y) = scale_coordinates(model, i, j);
+	let from = Point2::new(x, y);
+	draw.line()
+		.gray(gray)
+		.points(from, from + Vec2::new(angle.cos(), angle.sin()) * length);
+}
+
+/// An angle the noise map picks for each line, so the grid isn't all one way.
+fn scatter(model: &Model, i: usize, j: usize) -> f32 {
+	model.noisemap.get_value(i, j) as f32 * TAU
+}
+
+pub const SCENES: &[&Scene] = &[
+	&Scene {
+		title: Cow::Borrowed("Pulse"),
+		path: Cow::Borrowed("/fa/1.mp3"),
+		loop_: true,
+		measures: 14,
+		transition: Transition::Cut,
+		bands: Some(Cow::Borrowed(&LOW_END)),
+		waveform: 0,
+		chroma: false,
+		pattern: None,
+		// The lines pulse with the kick rather than flicker with every frame of it.
+		envelopes: Cow::Borrowed(&[(
+			Cow::Borrowed("bass"),
+			Envelope {
+				attack: 0.01,
+				release: 0.25,
+				hold: 0.06,
+				gain: 1.2,
+				curve: 1.5,
+			},
+		)]),
+		render: Render::Compiled(&|draw, model, i, j, _, modifiers| {
+			let bass = modifiers.envelope("bass").map_or(0.0, Follower::value);
+			let angle = scatter(model, i, j);
+			stroke(
+				draw,
+				model,
+				(i, j),
+				0.2 + 0.8 * bass,
+				angle,
+				5.0 + 30.0 * bass,
+			);
+		}),
+		boxes: Cow::Borrowed(&[MIDDLE]),
+	},
+	&Scene {
+		title: Cow::Borrowed("Kicks"),
+		path: Cow::Borrowed("/fa/2.mp3"),
+		loop_: true,
+		measures: 8,
+		transition: Transition::Crossfade(4.0),
+		bands: Some(Cow::Borrowed(&LOW_END)),
+		waveform: 0,
+		chroma: false,
+		pattern: None,
+		// Kicks flash the lines and fade out over a sixteenth or so.
+		envelopes: Cow::Borrowed(&[(
+			Cow::Borrowed("onset:sub"),
+			Envelope {
+				attack: 0.0,
+				release: 0.12,
+				hold: 0.0,
+				gain: 1.0,
+				curve: 1.0,
+			},
+		)]),
+		render: Render::Compiled(&|draw, model, i, j, _, modifiers| {
+			let kick = modifiers.envelope("onset:sub").map_or(0.0, Follower::value);
+			// Kicks panned to one side flash brighter on that side of the grid.
+			let column = i as f32 / (CANVAS_WIDTH - 1) as f32 * 2.0 - 1.0;
+			let kick = kick * (1.0 - (column - modifiers.pan("sub")).abs() / 2.0);
+			let angle = scatter(model, i, j);
+			stroke(draw, model, (i, j), 0.2 + 0.8 * kick, angle, 20.0);
+		}),
+		boxes: Cow::Borrowed(&[MIDDLE]),
+	},
+	&Scene {
+		title: Cow::Borrowed("Waveform"),
+		path: Cow::Borrowed("/fa/2.mp3"),
+		loop_: true,
+		measures: 8,
+		transition: Transition::Cut,
+		bands: Some(Cow::Borrowed(&LOW_END)),
+		// Each column of lines tilts with the waveform like an oscilloscope trace.
+		waveform: 1024,
+		chroma: false,
+		pattern: None,
+		envelopes: Cow::Borrowed(&[]),
+		render: Render::Compiled(&|draw, model, i, j, _, modifiers| {
+			let angle = FRAC_PI_2 * modifiers.waveform_column(i);
+			stroke(draw, model, (i, j), 0.6, angle, 20.0);
+		}),
+		boxes: Cow::Borrowed(&[MIDDLE]),
+	},
+	&Scene {
+		title: Cow::Borrowed("Harmony"),
+		path: Cow::Borrowed("/bu/2.mp3"),
+		loop_: true,
+		measures: 8,
+		transition: Transition::Cut,
+		bands: None,
+		waveform: 0,
+		chroma: true,
+		pattern: None,
+		envelopes: Cow::Borrowed(&[]),
+		render: Render::Compiled(&|draw, model, i, j, _, modifiers| {
+			// The lines turn round the circle of pitch classes as the harmony moves.
+			let angle = modifiers
+				.pitch_class
+				.map_or(FRAC_PI_4, |class| class as f32 / 12.0 * TAU);
+			stroke(draw, model, (i, j), 0.6, angle, 20.0);
+		}),
+		boxes: Cow::Borrowed(&[MIDDLE]),
+	},
+	&Scene {
+		title: Cow::Borrowed("Flashes"),
+		path: Cow::Borrowed("/bu/2.mp3"),
+		loop_: true,
+		measures: 8,
+		transition: Transition::Cut,
+		bands: None,
+		waveform: 0,
+		chroma: false,
+		// Flashes and throws the lines round on the steps of the pattern.
+		pattern: Some(Cow::Borrowed("/patterns/notes.txt")),
+		envelopes: Cow::Borrowed(&[(
+			Cow::Borrowed("hit:flash"),
+			Envelope {
+				attack: 0.0,
+				release: 0.2,
+				hold: 0.0,
+				gain: 1.0,
+				curve: 1.0,
+			},
+		)]),
+		render: Render::Compiled(&|draw, model, i, j, _, modifiers| {
+			let flash = modifiers.envelope("hit:flash").map_or(0.0, Follower::value);
+			let angle = scatter(model, i, j) + FRAC_PI_2 * modifiers.lane("throw");
+			stroke(draw, model, (i, j), 0.2 + 0.8 * flash, angle, 20.0);
+		}),
+		boxes: Cow::Borrowed(&[MIDDLE]),
+	},
+	&Scene {
+		title: Cow::Borrowed("Held notes"),
+		path: Cow::Borrowed("/fa/1.mp3"),
+		loop_: true,
+		measures: 14,
+		transition: Transition::Crossfade(4.0),
+		bands: Some(Cow::Borrowed(&LOW_END)),
+		waveform: 0,
+		chroma: false,
+		pattern: None,
+		envelopes: Cow::Borrowed(&[]),
+		render: Render::Compiled(&|draw, model, i, j, _, modifiers| {
+			// Rows light up while the arrangement holds a note of their pitch.
+			let held = (modifiers.held.iter())
+				.filter(|note| note.pitch as usize % CANVAS_HEIGHT == j)
+				.map(|note| note.velocity)
+				.fold(0.0, f32::max);
+			let angle = scatter(model, i, j);
+			stroke(draw, model, (i, j), 0.2 + 0.8 * held, angle, 20.0);
+		}),
+		boxes: Cow::Borrowed(&[MIDDLE]),
+	},
+];
@@ -1,3 +1,12 @@
-use crate::scenes::Scene;
+use crate::scenes::{Scene, Section};
+
+pub const SECTION: Section = Section {
+	name: "terra_firmament",
+	title: "Terra / Firmament",
+	words: &["terra", "firmament"],
+	order: 1,
+	enabled: true,
+	scenes: SCENES,
+};
 
 pub const SCENES: &[&Scene] = &[];
@@ -0,0 +1,124 @@
+//! What we know about each track's timing, so scenes can follow the music instead of assuming
+//! that a file is a whole number of evenly spaced measures.
+
+/// Where the beats of a track fall.
+#[derive(Clone, Copy, Debug)]
+pub struct BeatGrid {
+	pub bpm: f64,
+	/// Seconds before the first downbeat, any lead-in comes before it.
+	pub offset: f64,
+	pub beats_per_bar: usize,
+	/// Tempo changes in the order they happen.
+	pub tempo_changes: &'static [TempoChange],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TempoChange {
+	/// When the new tempo starts, in beats from the first downbeat.
+	pub beat: f64,
+	pub bpm: f64,
+}
+
+pub struct Track {
+	pub path: &'static str,
+	pub grid: BeatGrid,
+}
+
+pub const TRACKS: &[Track] = &[
+	Track {
+		path: "/bu/2.mp3",
+		grid: BeatGrid {
+			bpm: 110.0,
+			offset: 0.041,
+			beats_per_bar: 6,
+			tempo_changes: &[],
+		},
+	},
+	Track {
+		path: "/fa/1.mp3",
+		grid: BeatGrid {
+			bpm: 62.5,
+			offset: 0.524,
+			beats_per_bar: 4,
+			tempo_changes: &[],
+		},
+	},
+	Track {
+		path: "/fa/2.mp3",
+		grid: BeatGrid {
+			bpm: 62.5,
+			offset: 0.524,
+			beats_per_bar: 4,
+			tempo_changes: &[],
+		},
+	},
+	Track {
+		path: "/tf/1.mp3",
+		grid: BeatGrid {
+			bpm: 62.5,
+			offset: 0.524,
+			beats_per_bar: 4,
+			tempo_changes: &[],
+		},
+	},
+	Track {
+		path: "/tf/2.mp3",
+		grid: BeatGrid {
+			bpm: 62.5,
+			offset: 0.524,
+			beats_per_bar: 4,
+			tempo_changes: &[],
+		},
+	},
+];
+
+pub fn grid(path: &str) -> Option<&'static BeatGrid> {
+	TRACKS
+		.iter()
+		.find(|track| track.path == path)
+		.map(|track| &track.grid)
+}
+
+impl BeatGrid {
+	/// A steady 4/4 grid that fits exactly `measures` bars into `duration` seconds, for tracks we
+	/// know nothing else about.
+	pub fn spanning(duration: f64, measures: usize) -> Self {
+		BeatGrid {
+			bpm: measures as f64 * 4.0 * 60.0 / duration,
+			offset: 0.0,
+			beats_per_bar: 4,
+			tempo_changes: &[],
+		}
+	}
+
+	/// Beats since the first downbeat at `time` seconds, negative during the lead-in.
+	pub fn beat_at(&self, time: f64) -> f64 {
+		let (mut beat, mut start, mut bpm) = (0.0, self.offset, self.bpm);
+		for change in self.tempo_changes {
+			let end = start + (change.beat - beat) * 60.0 / bpm;
+			if time < end {
+				break;
+			}
+			(beat, start, bpm) = (change.beat, end, change.bpm);
+		}
+		beat + (time - start) * bpm / 60.0
+	}
+
+	/// Seconds into the track at which `beat` falls, the inverse of `beat_at`.
+	pub fn time_at(&self, beat: f64) -> f64 {
+		let (mut from, mut time, mut bpm) = (0.0, self.offset, self.bpm);
+		for change in self.tempo_changes {
+			if beat < change.beat {
+				break;
+			}
+			time += (change.beat - from) * 60.0 / bpm;
+			(from, bpm) = (change.beat, change.bpm);
+		}
+		time + (beat - from) * 60.0 / bpm
+	}
+
+	/// Bars since the first downbeat at `time` seconds.
+	pub fn bar_at(&self, time: f64) -> f64 {
+		self.beat_at(time) / self.beats_per_bar as f64
+	}
+}
@@ -0,0 +1,347 @@
+//! Musical position of the playing track, read off the audio clock so that no bar, beat or tick
+//! goes unnoticed however far apart frames are.
+
+use crate::tracks::BeatGrid;
+
+/// Ticks are sixteenth notes.
+pub const TICKS_PER_BEAT: i64 = 4;
+
+/// How far the transport may drift from the track's own position before it is re-anchored.
+const TOLERANCE: f64 = 0.05;
+
+/// The largest boundary a tick falls on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+	/// A bar line.
+	Bar,
+	Beat,
+	Tick,
+}
+
+/// A tick the transport passed.
+#[derive(Clone, Copy, Debug)]
+pub struct Event {
+	pub boundary: Boundary,
+	/// Times the track had looped by then.
+	pub loops: i64,
+	/// Bars since the first downbeat, negative during the lead-in.
+	pub bar: i64,
+	pub beat: usize,
+	pub tick: usize,
+	/// When it fell, on the audio clock.
+	pub time: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Position {
+	pub loops: i64,
+	pub bar: i64,
+	/// Beats into the bar, fractional.
+	pub beat: f64,
+}
+
+pub struct Transport {
+	grid: BeatGrid,
+	duration: f64,
+	loop_: bool,
+
+	/// Audio clock time the track was last anchored at, `None` while paused.
+	anchor_clock: Option<f64>,
+	/// Track time at `anchor_clock`, counting on through loops.
+	anchor_time: f64,
+	time: f64,
+	tick: i64,
+}
+
+impl Transport {
+	pub fn new(grid: BeatGrid, duration: f64, loop_: bool) -> Self {
+		Transport {
+			grid,
+			duration,
+			loop_,
+			anchor_clock: None,
+			anchor_time: 0.0,
+			time: 0.0,
+			tick: 0,
+		}
+	}
+
+	/// Switches to another grid or track length, without reporting the jump as boundaries.
+	pub fn set_track(&mut self, grid: BeatGrid, duration: f64, loop_: bool) {
+		let unchanged = self.grid.bpm == grid.bpm
+			&& self.grid.offset == grid.offset
+			&& self.grid.beats_per_bar == grid.beats_per_bar
+			&& std::ptr::eq(self.grid.tempo_changes, grid.tempo_changes)
+			&& self.duration.to_bits() == duration.to_bits()
+			&& self.loop_ == loop_;
+		if !unchanged {
+			(self.grid, self.duration, self.loop_) = (grid, duration, loop_);
+			self.tick = self.tick_at(self.time);
+		}
+	}
+
+	/// Starts following the audio clock from `time` seconds into the track.
+	pub fn play(&mut self, clock: f64, time: f64) {
+		self.anchor_clock = Some(clock);
+		self.anchor_time = time;
+		self.time = time;
+		self.tick = self.tick_at(time);
+	}
+
+	pub fn pause(&mut self, clock: f64) {
+		if let Some(anchor) = self.anchor_clock.take() {
+			self.anchor_time += clock - anchor;
+			self.time = self.anchor_time;
+		}
+	}
+
+	pub fn playing(&self) -> bool {
+		self.anchor_clock.is_some()
+	}
+
+	/// Re-anchors to the track's own position, `time`, if the clock and the track have drifted
+	/// apart, which mostly happens when playback starts later than it was asked to. Ticks already
+	/// reported are not reported again.
+	pub fn follow(&mut self, clock: f64, time: f64) {
+		let Some(anchor) = self.anchor_clock else {
+			return;
+		};
+		let expected = self.anchor_time + clock - anchor;
+		let (loops, _) = self.split(expected);
+		let mut actual = time + loops as f64 * self.loop_length();
+		// The track may have looped just before or after the transport expected it to.
+		if self.looping() {
+			let length = self.loop_length();
+			if actual < expected - length / 2.0 {
+				actual += length;
+			} else if actual > expected + length / 2.0 {
+				actual -= length;
+			}
+		}
+		if (actual - expected).abs() > TOLERANCE {
+			self.anchor_clock = Some(clock);
+			self.anchor_time = actual;
+		}
+	}
+
+	/// Moves up to `clock` and returns every tick passed since the last call, in order.
+	pub fn advance(&mut self, clock: f64) -> Vec<Event> {
+		let Some(anchor) = self.anchor_clock else {
+			return vec![];
+		};
+		self.time = self.anchor_time + clock - anchor;
+		let tick = self.tick_at(self.time);
+		let events = (self.tick + 1..=tick)
+			.map(|tick| self.event(tick, anchor))
+			.collect();
+		self.tick = self.tick.max(tick);
+		events
+	}
+
+	/// The next bar line after the last tick reported, `None` while paused.
+	pub fn next_bar(&self) -> Option<Event> {
+		let anchor = self.anchor_clock?;
+		let ticks_per_bar = TICKS_PER_BEAT * self.grid.beats_per_bar as i64;
+		(self.tick + 1..=self.tick + ticks_per_bar)
+			.map(|tick| self.event(tick, anchor))
+			.find(|event| event.boundary == Boundary::Bar)
+	}
+
+	pub fn position(&self) -> Position {
+		let (loops, time) = self.split(self.time);
+		let beat = self.grid.beat_at(time);
+		let beats_per_bar = self.grid.beats_per_bar as f64;
+		Position {
+			loops,
+			bar: (beat / beats_per_bar).floor() as i64,
+			beat: beat.rem_euclid(beats_per_bar),
+		}
+	}
+
+	fn looping(&self) -> bool {
+		self.loop_ && self.duration.is_finite() && self.duration > 0.0
+	}
+
+	fn loop_length(&self) -> f64 {
+		if self.looping() {
+			self.duration
+		} else {
+			0.0
+		}
+	}
+
+	/// Loops so far and the time into the current one.
+	fn split(&self, time: f64) -> (i64, f64) {
+		if self.looping() {
+			let loops = (time / self.duration).floor();
+			(loops as i64, time - loops * self.duration)
+		} else if self.duration.is_finite() {
+			(0, time.min(self.duration))
+		} else {
+			(0, time)
+		}
+	}
+
+	/// The first tick of the track, which is negative if it has a lead-in.
+	fn first_tick(&self) -> i64 {
+		(self.grid.beat_at(0.0) * TICKS_PER_BEAT as f64).floor() as i64
+	}
+
+	/// Ticks in one pass of the track. A last tick cut shorter than half by the loop point isn't
+	/// counted, so it can't land right on top of the restart.
+	fn ticks_per_loop(&self) -> i64 {
+		let last = self.grid.beat_at(self.duration) * TICKS_PER_BEAT as f64;
+		(last.round() as i64 - self.first_tick()).max(1)
+	}
+
+	/// Ticks since the start of the track at `time`, counting on through loops.
+	fn tick_at(&self, time: f64) -> i64 {
+		let (loops, time) = self.split(time);
+		let tick = (self.grid.beat_at(time) * TICKS_PER_BEAT as f64).floor() as i64;
+		if self.looping() {
+			loops * self.ticks_per_loop() + (tick - self.first_tick()).min(self.ticks_per_loop())
+		} else {
+			tick - self.first_tick()
+		}
+	}
+
+	fn event(&self, tick: i64, anchor: f64) -> Event {
+		let (loops, local) = if self.looping() {
+			let ticks = self.ticks_per_loop();
+			(tick.div_euclid(ticks), tick.rem_euclid(ticks))
+		} else {
+			(0, tick)
+		};
+		let musical = local + self.first_tick();
+		let beats_per_bar = self.grid.beats_per_bar as i64;
+		let beat = musical.div_euclid(TICKS_PER_BEAT);
+		let time = loops as f64 * self.loop_length()
+			+ self
+				.grid
+				.time_at(musical as f64 / TICKS_PER_BEAT as f64)
+				.max(0.0);
+
+		Event {
+			boundary: if musical.rem_euclid(TICKS_PER_BEAT * beats_per_bar) == 0 {
+				Boundary::Bar
+			} else if musical.rem_euclid(TICKS_PER_BEAT) == 0 {
+				Boundary::Beat
+			} else {
+				Boundary::Tick
+			},
+			loops,
+			bar: beat.div_euclid(beats_per_bar),
+			beat: beat.rem_euclid(beats_per_bar) as usize,
+			tick: musical.rem_euclid(TICKS_PER_BEAT) as usize,
+			time: anchor + time - self.anchor_time,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// 4/4 at 120 BPM: a tick every eighth of a second and a bar every two seconds, the first
+	/// downbeat `offset` seconds in.
+	fn grid(offset: f64) -> BeatGrid {
+		BeatGrid {
+			bpm: 120.0,
+			offset,
+			beats_per_bar: 4,
+			tempo_changes: &[],
+		}
+	}
+
+	/// Loop, bar and time of each bar line in `events`.
+	fn bars(events: &[Event]) -> Vec<(i64, i64, f64)> {
+		(events.iter())
+			.filter(|event| event.boundary == Boundary::Bar)
+			.map(|event| (event.loops, event.bar, event.time))
+			.collect()
+	}
+
+	#[test]
+	fn a_long_frame_reports_every_tick_it_spans() {
+		let mut transport = Transport::new(grid(0.0), 60.0, false);
+		transport.play(0.0, 0.0);
+		let events = transport.advance(5.0);
+		assert_eq!(events.len(), 40);
+		assert_eq!(bars(&events), vec![(0, 1, 2.0), (0, 2, 4.0)]);
+		let beats = events
+			.iter()
+			.filter(|event| event.boundary == Boundary::Beat);
+		assert_eq!(beats.count(), 8);
+		assert!(events.windows(2).all(|pair| pair[0].time < pair[1].time));
+		let last = events.last().unwrap();
+		assert_eq!((last.bar, last.beat, last.tick), (2, 2, 0));
+		assert!(transport.advance(5.0).is_empty());
+	}
+
+	#[test]
+	fn looping_tracks_count_on_through_the_loop_point() {
+		// Two bars long.
+		let mut transport = Transport::new(grid(0.0), 4.0, true);
+		transport.play(0.0, 0.0);
+		let events = transport.advance(9.0);
+		assert_eq!(events.len(), 72);
+		assert_eq!(
+			bars(&events),
+			vec![(0, 1, 2.0), (1, 0, 4.0), (1, 1, 6.0), (2, 0, 8.0)]
+		);
+		let position = transport.position();
+		assert_eq!((position.loops, position.bar), (2, 0));
+		assert!((position.beat - 2.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn a_lead_in_comes_before_the_first_bar() {
+		let mut transport = Transport::new(grid(0.25), 60.0, false);
+		transport.play(0.0, 0.0);
+		let events = transport.advance(2.3);
+		// The lead-in's last tick, half a beat before the first downbeat.
+		let first = events[0];
+		assert_eq!((first.bar, first.beat, first.tick), (-1, 3, 3));
+		assert_eq!(first.boundary, Boundary::Tick);
+		assert_eq!(bars(&events), vec![(0, 0, 0.25), (0, 1, 2.25)]);
+	}
+
+	#[test]
+	fn a_looping_lead_in_only_has_its_own_bar_lines() {
+		// Half a beat of lead-in, then two bars.
+		let mut transport = Transport::new(grid(0.25), 4.25, true);
+		transport.play(0.0, 0.0);
+		let events = transport.advance(8.5);
+		let bars = bars(&events)
+			.into_iter()
+			.map(|(loops, bar, _)| (loops, bar))
+			.collect::<Vec<_>>();
+		assert_eq!(bars, vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+	}
+
+	#[test]
+	fn follow_re_anchors_when_the_track_drifts() {
+		let mut transport = Transport::new(grid(0.0), 60.0, false);
+		transport.play(0.0, 0.0);
+		assert_eq!(transport.advance(1.0).len(), 8);
+
+		// Within the tolerance the clock keeps time.
+		transport.follow(1.0, 1.02);
+		assert!(transport.advance(1.0).is_empty());
+		assert!((transport.position().beat - 2.0).abs() < 1e-9);
+
+		// The track got ahead, so the ticks it passed are caught up on.
+		transport.follow(1.0, 1.3);
+		let ticks = (transport.advance(1.0).iter())
+			.map(|event| (event.beat, event.tick))
+			.collect::<Vec<_>>();
+		assert_eq!(ticks, vec![(2, 1), (2, 2)]);
+		assert!((transport.position().beat - 2.6).abs() < 1e-9);
+
+		// It fell back behind the last tick reported, which isn't reported again.
+		transport.follow(2.0, 1.2);
+		assert!(transport.advance(2.0).is_empty());
+		assert!((transport.position().beat - 2.4).abs() < 1e-9);
+		assert_eq!(transport.advance(2.5).len(), 3);
+	}
+}
@@ -0,0 +1,6 @@
+cd app
+echo "Building wasm target..."
+wasm-pack build --target web
+cd ..
+echo "Building server, watching static/ for changes..."
+cargo run -- --dev
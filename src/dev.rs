@@ -0,0 +1,64 @@
+//! Development mode: the server watches `static/` and tells the app what changed over server-sent
+//! events at `/events`, so it can load scenes, patterns and scripts again without a restart.
+
+use {
+	actix_web::{http::header, web, HttpResponse},
+	futures_util::stream,
+	notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher},
+	std::{convert::Infallible, path::Path},
+	tokio::sync::broadcast::{self, error::RecvError, Sender},
+};
+
+/// Changes a client can fall behind on before it misses some.
+const BACKLOG: usize = 64;
+
+/// Where changes to the files under `dir` are announced, as the server hosts them, like
+/// `/scenes.ron`. Changes stop being watched when the watcher is dropped.
+pub fn watch(dir: &str) -> notify::Result<(RecommendedWatcher, Sender<String>)> {
+	let root = Path::new(dir).canonicalize()?;
+	let (changes, _) = broadcast::channel(BACKLOG);
+	let sender = changes.clone();
+	let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+		let event = match event {
+			Ok(event) => event,
+			Err(error) => return log::warn!("watching static files: {error}"),
+		};
+		// Editors save by writing in place or by renaming a new file over the old one.
+		if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+			return;
+		}
+		for path in event.paths {
+			let Ok(relative) = path.strip_prefix(&root) else {
+				continue;
+			};
+			let hosted = (relative.components())
+				.map(|component| format!("/{}", component.as_os_str().to_string_lossy()))
+				.collect::<String>();
+			log::info!("changed: {hosted}");
+			// Failing only means nobody is listening.
+			let _ = sender.send(hosted);
+		}
+	})?;
+	watcher.watch(Path::new(dir), RecursiveMode::Recursive)?;
+	Ok((watcher, changes))
+}
+
+/// A stream of the paths that change from now on, one event each.
+pub async fn events(changes: web::Data<Sender<String>>) -> HttpResponse {
+	let changed = stream::unfold(changes.subscribe(), |mut receiver| async move {
+		loop {
+			match receiver.recv().await {
+				Ok(path) => {
+					let event = web::Bytes::from(format!("data: {path}\n\n"));
+					return Some((Ok::<_, Infallible>(event), receiver));
+				}
+				Err(RecvError::Lagged(_)) => continue,
+				Err(RecvError::Closed) => return None,
+			}
+		}
+	});
+	HttpResponse::Ok()
+		.insert_header((header::CACHE_CONTROL, "no-cache"))
+		.content_type("text/event-stream")
+		.streaming(changed)
+}
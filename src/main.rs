@@ -1,16 +1,44 @@
 use actix_files::Files;
-use actix_web::{middleware::Logger, App, HttpServer};
+use actix_web::{
+	middleware::{Condition, DefaultHeaders, Logger},
+	web, App, HttpServer,
+};
+
+mod dev;
+
+/// Where the files the app loads are hosted from.
+const STATIC_DIR: &str = "./static";
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
 	env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
+	// `--dev` watches the static files and tells the app when they change.
+	let dev = std::env::args().any(|argument| argument == "--dev");
+	let (_watcher, changes) = if dev {
+		let (watcher, changes) = dev::watch(STATIC_DIR).map_err(std::io::Error::other)?;
+		log::info!("watching {STATIC_DIR} for changes, announced at /events");
+		(Some(watcher), Some(web::Data::new(changes)))
+	} else {
+		(None, None)
+	};
+
 	log::info!("starting HTTP server at http://localhost:8080");
 
-	HttpServer::new(|| {
-		App::new()
-			.service(Files::new("/app", "./app/pkg").show_files_listing())
-			.service(Files::new("/", "./static").index_file("index.html"))
+	HttpServer::new(move || {
+		let mut app = App::new();
+		if let Some(changes) = &changes {
+			app = app
+				.app_data(changes.clone())
+				.route("/events", web::get().to(dev::events));
+		}
+		app.service(Files::new("/app", "./app/pkg").show_files_listing())
+			.service(Files::new("/", STATIC_DIR).index_file("index.html"))
+			// Files that change while developing have to be fetched again every time.
+			.wrap(Condition::new(
+				dev,
+				DefaultHeaders::new().add(("Cache-Control", "no-cache")),
+			))
 			.wrap(Logger::default())
 	})
 	.bind(("127.0.0.1", 8080))?